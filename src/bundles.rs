@@ -0,0 +1,89 @@
+use cosmwasm_std::Uint128;
+
+use crate::ContractError;
+use crate::state::{Allocation, Lot};
+
+/// Winner determination is exact, so the number of lots stays small to keep the gas of `close`
+/// predictable: it grows with `3^lots`.
+pub const MAX_LOTS: usize = 8;
+
+pub fn validate_lots(lots: &[Lot], quantity: Option<u64>) -> Result<(), ContractError> {
+    if lots.is_empty() {
+        return Ok(());
+    }
+    if quantity.is_some() || lots.len() > MAX_LOTS {
+        return Err(ContractError::InvalidLots {});
+    }
+    for (i, lot) in lots.iter().enumerate() {
+        if lots[..i].iter().any(|other| other.id == lot.id) {
+            return Err(ContractError::InvalidLots {});
+        }
+    }
+    Ok(())
+}
+
+/// Sorts and deduplicates the lot ids a bid targets, rejecting unknown ones.
+pub fn normalize(lots: &[Lot], mut ids: Vec<u32>) -> Result<Vec<u32>, ContractError> {
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.is_empty() || ids.iter().any(|id| !lots.iter().any(|lot| lot.id == *id)) {
+        return Err(ContractError::InvalidLots {});
+    }
+    Ok(ids)
+}
+
+fn mask(lots: &[Lot], ids: &[u32]) -> usize {
+    ids.iter()
+        .filter_map(|id| lots.iter().position(|lot| lot.id == *id))
+        .fold(0, |mask, i| mask | 1 << i)
+}
+
+/// Picks the bids on pairwise disjoint lots with the highest total price. `bids` are expected
+/// highest first, so among equal bids on the same lots the earlier one wins.
+pub fn best_bundles(lots: &[Lot], bids: Vec<Allocation>) -> Vec<Allocation> {
+    let full = (1usize << lots.len()) - 1;
+
+    // Only the highest bid on each exact set of lots can win.
+    let mut best: Vec<Option<Allocation>> = vec![None; full + 1];
+    for bid in bids {
+        let mask = mask(lots, &bid.lots);
+        if best[mask].as_ref().map_or(true, |other| bid.price > other.price) {
+            best[mask] = Some(bid);
+        }
+    }
+
+    // `revenue[mask]` is the most the lots in `mask` can bring in, `choice[mask]` the bundle
+    // sold together with the lowest lot in `mask`, or zero if that lot stays unsold.
+    let mut revenue = vec![Uint128::zero(); full + 1];
+    let mut choice = vec![0usize; full + 1];
+    for mask in 1..=full {
+        let lowest = mask & mask.wrapping_neg();
+        revenue[mask] = revenue[mask ^ lowest];
+
+        let mut subset = mask;
+        while subset > 0 {
+            if let Some(bid) = best[subset].as_ref().filter(|_| subset & lowest != 0) {
+                let total = bid.price + revenue[mask ^ subset];
+                if total > revenue[mask] {
+                    revenue[mask] = total;
+                    choice[mask] = subset;
+                }
+            }
+            subset = (subset - 1) & mask;
+        }
+    }
+
+    let mut winners = vec![];
+    let mut mask = full;
+    while mask > 0 {
+        let lowest = mask & mask.wrapping_neg();
+        match choice[mask] {
+            0 => mask ^= lowest,
+            subset => {
+                winners.extend(best[subset].take());
+                mask ^= subset;
+            }
+        }
+    }
+    winners
+}
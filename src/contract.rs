@@ -1,17 +1,38 @@
-use cosmwasm_std::{Binary, coin, Coin, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult, to_binary};
+use cosmwasm_std::{
+    Binary, coin, Coin, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult, Storage, to_binary,
+    Uint128,
+};
+#[cfg(debug_assertions)]
+use cosmwasm_std::{BankMsg, CosmosMsg, from_binary, StdError, WasmMsg};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cw2::set_contract_version;
 
+use crate::{bundles, vault};
 use crate::error::ContractError;
-use crate::msg::{Bid, ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{BIDS, Config, CONFIG};
+use crate::events;
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+#[cfg(debug_assertions)]
+use crate::msg::VaultExecuteMsg;
+use crate::state::{
+    AcceptedDenom, Allocation, CLOSED, Config, CONFIG, FEES, MemberGroup, OWNER_DEPOSIT, PROXY_BIDS, Royalty, Vault,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const DENOM: &str = "atom";
+/// Reply id of outbid hooks, which only reply on error.
+pub(crate) const OUTBID_HOOK_REPLY_ID: u64 = 0;
+/// Gas each outbid hook may use. Without a limit a hook running out of gas would abort the bid
+/// that outbid its bidder, so the leader could never be outbid.
+pub const OUTBID_HOOK_GAS_LIMIT: u64 = 100_000;
+/// Reply id of the settlement hook sent for the first winner; the others follow in order.
+pub(crate) const SETTLEMENT_HOOK_REPLY_ID: u64 = 1;
+/// Gas each settlement hook may use. Without a limit a hook running out of gas would abort the
+/// whole close instead of only failing itself.
+pub const SETTLEMENT_HOOK_GAS_LIMIT: u64 = 300_000;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -24,216 +45,1638 @@ pub fn instantiate(
 
     let owner = msg.owner.unwrap_or_else(|| info.sender.to_string());
     let validated_owner = deps.api.addr_validate(&owner)?;
+    let fee_recipient = match msg.fee_recipient {
+        Some(fee_recipient) => deps.api.addr_validate(&fee_recipient)?,
+        None => validated_owner.clone(),
+    };
+    let denoms = if msg.denoms.is_empty() {
+        vec![AcceptedDenom { denom: DENOM.to_string(), weight: Decimal::one() }]
+    } else {
+        msg.denoms
+    };
+    validate_denoms(&denoms)?;
+    if msg.quantity == Some(0) || (msg.quantity.is_some() && denoms.len() > 1) {
+        return Err(ContractError::InvalidQuantity {});
+    }
+    bundles::validate_lots(&msg.lots, msg.quantity)?;
+    let member_group = match msg.member_group {
+        Some(group) => Some(MemberGroup {
+            contract: deps.api.addr_validate(&group.contract)?,
+            min_weight: group.min_weight,
+        }),
+        None => None,
+    };
+    let settlement_hook = msg.settlement_hook.map(|hook| deps.api.addr_validate(&hook)).transpose()?;
+    let royalties = msg.royalties.into_iter()
+        .map(|royalty| Ok(Royalty { address: deps.api.addr_validate(&royalty.address)?, share: royalty.share }))
+        .collect::<StdResult<Vec<_>>>()?;
+    validate_royalties(&royalties)?;
+    let vault = match msg.vault {
+        Some(vault) => Some(Vault {
+            contract: deps.api.addr_validate(&vault.contract)?,
+            yield_recipient: vault.yield_recipient,
+        }),
+        None => None,
+    };
+
     let config = Config {
         owner: validated_owner.clone(),
         commodity: msg.commodity,
+        fee_recipient,
+        charge_losing_bidders: msg.charge_losing_bidders.unwrap_or(true),
+        denoms,
+        access_mode: msg.access_mode.unwrap_or_default(),
+        member_group,
+        max_bid_per_address: msg.max_bid_per_address,
+        max_total_escrow: msg.max_total_escrow,
+        quantity: msg.quantity,
+        lots: msg.lots,
+        min_increment: msg.min_increment.unwrap_or_default(),
+        starting_price: msg.starting_price.unwrap_or_default(),
+        settlement_hook,
+        royalties,
+        vault,
     };
     CONFIG.save(deps.storage, &config)?;
+    CLOSED.save(deps.storage, &false)?;
+    FEES.save(deps.storage, &vec![])?;
 
-    let empty_bid = coin(0, DENOM);
-    let bid = info.funds.iter().find(|coin| {
-        coin.denom == DENOM
-    }).unwrap_or_else(|| {
-        &empty_bid
-    });
+    let deposit = if info.funds.is_empty() {
+        vec![]
+    } else {
+        bid_value(&config, &info.funds)?.0
+    };
+    OWNER_DEPOSIT.save(deps.storage, &deposit)?;
 
-    BIDS.save(deps.storage, validated_owner, bid)?;
+    Ok(Response::new()
+        .add_event(events::auction_created(&validated_owner, &config.commodity, &deposit)))
+}
 
-    Ok(Response::default())
+/// The first denom is the base all bids are compared in, so it has to have a weight of one.
+fn validate_denoms(denoms: &[AcceptedDenom]) -> Result<(), ContractError> {
+    if denoms[0].weight != Decimal::one() {
+        return Err(ContractError::InvalidDenoms {});
+    }
+    for (i, accepted) in denoms.iter().enumerate() {
+        if accepted.weight.is_zero() || denoms[..i].iter().any(|other| other.denom == accepted.denom) {
+            return Err(ContractError::InvalidDenoms {});
+        }
+    }
+    Ok(())
+}
+
+/// Royalties come out of the winning amount alongside the commission, so together they cannot
+/// take more than all of it.
+fn validate_royalties(royalties: &[Royalty]) -> Result<(), ContractError> {
+    let mut total = Decimal::percent(DEFAULT_COMMISSION);
+    for royalty in royalties {
+        if royalty.share.is_zero() {
+            return Err(ContractError::InvalidRoyalties {});
+        }
+        total = total.checked_add(royalty.share).map_err(|_| ContractError::InvalidRoyalties {})?;
+    }
+    if total > Decimal::one() {
+        return Err(ContractError::InvalidRoyalties {});
+    }
+    Ok(())
+}
+
+/// Splits the funds into the escrowed coins and their value in the base denom.
+fn bid_value(config: &Config, funds: &[Coin]) -> Result<(Vec<Coin>, Coin), ContractError> {
+    let mut value = Uint128::zero();
+    let mut deposit = vec![];
+    for fund in funds {
+        let accepted = config.denoms.iter().find(|accepted| {
+            accepted.denom == fund.denom
+        }).ok_or_else(|| ContractError::UnsupportedDenom { denom: fund.denom.clone() })?;
+
+        if !fund.amount.is_zero() {
+            value = value.checked_add(weighted(fund.amount, accepted.weight)?)
+                .map_err(|_| ContractError::Overflow {})?;
+            add_coin(&mut deposit, fund);
+        }
+    }
+    if deposit.is_empty() {
+        return Err(ContractError::EmptyBid {});
+    }
+    Ok((deposit, coin(value.u128(), config.base_denom())))
+}
+
+/// `amount` in the base denom, rounded down.
+pub(crate) fn weighted(amount: Uint128, weight: Decimal) -> Result<Uint128, ContractError> {
+    amount
+        .checked_multiply_ratio(weight.atomics(), Decimal::one().atomics())
+        .map_err(|_| ContractError::Overflow {})
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    let resp = dispatch(deps.branch(), env.clone(), info, msg)?;
+    let resp = vault::rebalance(deps.storage, resp)?;
+    #[cfg(debug_assertions)]
+    check_solvency(deps.as_ref(), &env, &resp)?;
+    Ok(resp)
+}
+
+fn dispatch(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::MakeBid {} => exec::make_bid(deps, env, info),
+        ExecuteMsg::MakeBid { units, price, lots, expires, on_behalf_of } => {
+            exec::make_bid(deps, env, info, units, price, lots, expires, on_behalf_of)
+        }
+        ExecuteMsg::SetMaxBid { max } => exec::set_max_bid(deps, env, info, max),
         ExecuteMsg::Close {} => exec::close(deps, env, info),
-        ExecuteMsg::Retract { receiver } => exec::retract(deps, env, info, receiver),
+        ExecuteMsg::Retract { receiver, on_behalf_of } => exec::retract(deps, env, info, receiver, on_behalf_of),
+        ExecuteMsg::DecreaseBid { amount } => exec::decrease_bid(deps, env, info, amount),
+        ExecuteMsg::ClaimFees {} => exec::claim_fees(deps, env, info),
+        ExecuteMsg::ApproveOperator { operator, expires, max_amount } => {
+            exec::approve_operator(deps, env, info, operator, expires, max_amount)
+        }
+        ExecuteMsg::RevokeOperator { operator } => exec::revoke_operator(deps, env, info, operator),
+        ExecuteMsg::RaiseBid { granter, amount } => exec::raise_bid(deps, env, info, granter, amount),
+        ExecuteMsg::PruneExpired { limit } => exec::prune_expired(deps, env, info, limit),
+        ExecuteMsg::UpdateAccessList { add, remove } => exec::update_access_list(deps, env, info, add, remove),
+        ExecuteMsg::RegisterOutbidHook { contract } => exec::register_outbid_hook(deps, env, info, contract),
+        ExecuteMsg::Withdraw { receiver } => exec::withdraw(deps, env, info, receiver),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        OUTBID_HOOK_REPLY_ID => reply::outbid_hook(msg.result),
+        id if id >= SETTLEMENT_HOOK_REPLY_ID => reply::settlement_hook(deps, id, msg.result),
+        id => Err(ContractError::UnknownReply { id }),
+    }
+}
+
+/// Fails when the balance left after the transfers in `resp`, together with the vault, does not
+/// cover everything the contract owes. Only checked in debug builds.
+#[cfg(debug_assertions)]
+fn check_solvency(deps: Deps, env: &Env, resp: &Response) -> Result<(), ContractError> {
+    let accounting = query::query_accounting(deps, env)?;
+    let vault = CONFIG.load(deps.storage)?.vault.map(|vault| vault.contract);
+    let mut balance = accounting.balance;
+    accounting.vault.iter().for_each(|deposited| add_coin(&mut balance, deposited));
+    for msg in &resp.messages {
+        match &msg.msg {
+            CosmosMsg::Bank(BankMsg::Send { amount, .. }) => {
+                amount.iter().try_for_each(|sent| sub_coin(&mut balance, sent))?;
+            }
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }) => {
+                funds.iter().try_for_each(|sent| sub_coin(&mut balance, sent))?;
+                if vault.as_ref().map_or(false, |vault| vault == contract_addr) {
+                    if let VaultExecuteMsg::Withdraw { amount } = from_binary(msg)? {
+                        amount.iter().for_each(|withdrawn| add_coin(&mut balance, withdrawn));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut owed = vec![];
+    for coins in [
+        &accounting.payout_owed,
+        &accounting.refundable,
+        &accounting.fees,
+        &accounting.reserves,
+        &accounting.claimable,
+    ] {
+        coins.iter().for_each(|amount| add_coin(&mut owed, amount));
+    }
+    for amount in &owed {
+        let held = balance.iter().find(|held| held.denom == amount.denom).map(|held| held.amount).unwrap_or_default();
+        if held < amount.amount {
+            return Err(StdError::generic_err(format!("Insolvent: owes {} but holds {}{}", amount, held, amount.denom)).into());
+        }
     }
+    Ok(())
 }
 
 pub mod exec {
-    use cosmwasm_std::{BankMsg, coin, DepsMut, Env, MessageInfo, Response, Uint128};
+    use cosmwasm_std::{
+        Addr, BankMsg, coin, Coin, Deps, DepsMut, Empty, Env, Event, MessageInfo, Order, Response, StdResult, Storage,
+        SubMsg, to_binary, Uint128, WasmMsg,
+    };
+
+    use cw4::Cw4Contract;
+    use cw_utils::{nonpayable, Expiration};
+
+    use crate::{bundles, ContractError, events, vault};
+    use crate::contract::{
+        add_coin, bid_value, Commission, non_zero, OUTBID_HOOK_GAS_LIMIT, OUTBID_HOOK_REPLY_ID, paid_amounts, query, Settlement, settlement,
+        SETTLEMENT_HOOK_GAS_LIMIT, SETTLEMENT_HOOK_REPLY_ID, sub_coin, weighted, winner_payment,
+    };
+    use crate::msg::{Bid, OutbidHookMsg, SettlementHookMsg};
+    use crate::state::{
+        ACCESS_LIST, AccessMode, Allocation, Approval, BIDS, BUNDLE_BIDS, CLAIMABLE, CLOSED, COMMISSIONS, Config, CONFIG, ESCROW,
+        EXPIRIES, FEES, OPERATORS, OUTBID_HOOKS, OWNER_DEPOSIT, PAYERS, PROXY_BIDS, SETTLEMENT_HOOKS, UNIT_BIDS, UnitBid,
+        WINNERS, YieldRecipient,
+    };
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn make_bid(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        units: Option<u64>,
+        price: Option<Uint128>,
+        lots: Option<Vec<u32>>,
+        expires: Option<Expiration>,
+        on_behalf_of: Option<String>,
+    ) -> Result<Response, ContractError> {
+        let bidder = match on_behalf_of {
+            Some(beneficiary) => deps.api.addr_validate(&beneficiary)?,
+            None => info.sender.clone(),
+        };
+        let resp = place_bid(deps, &env, &info.sender, bidder, &info.funds, units, price, lots, expires)?
+            .add_attribute("action", "bid")
+            .add_attribute("sender", info.sender.as_str());
 
-    use crate::{ContractError};
-    use crate::contract::{Commission, DENOM, query};
-    use crate::msg::Bid;
-    use crate::state::{BIDS, CONFIG};
+        Ok(resp)
+    }
 
-    pub fn make_bid(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    /// Places a bid for `bidder` funded by `payer` with `funds`.
+    #[allow(clippy::too_many_arguments)]
+    fn place_bid(
+        deps: DepsMut,
+        env: &Env,
+        payer: &Addr,
+        bidder: Addr,
+        funds: &[Coin],
+        units: Option<u64>,
+        price: Option<Uint128>,
+        lots: Option<Vec<u32>>,
+        expires: Option<Expiration>,
+    ) -> Result<Response, ContractError> {
         let config = CONFIG.load(deps.storage)?;
-        if info.sender == config.owner {
+        check_bidder(deps.as_ref(), &config, &bidder)?;
+        // The owner funding someone else's bid would be shill bidding all the same.
+        if *payer == config.owner {
             return Err(ContractError::OwnerCannotBid {});
         }
+        check_payer(deps.storage, &bidder, payer)?;
+        if lots.is_some() == config.lots.is_empty() {
+            return Err(ContractError::InvalidLots {});
+        }
+        if expires.map_or(false, |expires| expires.is_expired(&env.block)) {
+            return Err(ContractError::InvalidExpiration {});
+        }
 
-        let fund = info.funds.iter().find(|coin| {
-            coin.denom == DENOM
-        });
-        if fund.is_none() {
-            return Err(ContractError::EmptyBid {});
-        }
-
-        let resp = query::query_bids(deps.as_ref())?;
-        let new_bid = Bid { address: info.sender.clone(), coin: fund.unwrap().clone() };
-        let summarized_bid: Bid = resp.bids.iter().find(|bid| {
-            bid.address == info.sender
-        }).map(|bid| {
-            let amount = bid.coin.amount + new_bid.coin.amount;
-            Bid { address: info.sender.clone(), coin: coin(amount.u128(), DENOM) }
-        }).unwrap_or_else(|| {
-            let amount = new_bid.coin.amount;
-            Bid { address: info.sender.clone(), coin: coin(amount.u128(), DENOM) }
-        });
+        let (deposit, value) = bid_value(&config, funds)?;
+        let leaders = query::query_winners(deps.as_ref(), env)?.winners;
 
-        let old_bid = resp.bids.first();
-        if old_bid.is_some() && old_bid.unwrap().address != info.sender.clone() {
-            if old_bid.unwrap().coin.amount >= summarized_bid.coin.amount {
-                return Err(ContractError::BidTooLow {
-                    amount: summarized_bid.coin.amount,
-                    required: old_bid.unwrap().coin.amount,
-                });
+        // Expired bids are left out of the bids, but topping one up revives the whole bid.
+        let resp = query::query_bids(deps.as_ref(), env)?;
+        let summarized_bid = match BIDS.may_load(deps.storage, bidder.clone())? {
+            Some(bid) => {
+                let amount = bid.amount.checked_add(value.amount).map_err(|_| ContractError::Overflow {})?;
+                Bid { address: bidder.clone(), coin: coin(amount.u128(), &value.denom) }
             }
+            None => Bid { address: bidder.clone(), coin: value.clone() },
+        };
+
+        let mut raised = vec![];
+        match (config.quantity, units, price) {
+            // A multi-unit bid replaces the bidder's previous one, topped up to cover all units.
+            (Some(quantity), Some(units), Some(price)) => {
+                if units == 0 || units > quantity || price.is_zero() {
+                    return Err(ContractError::InvalidUnitBid {});
+                }
+                check_starting_price(&config, price)?;
+                check_floor(deps.storage, &config, price)?;
+                let required = price.checked_mul(Uint128::from(units))
+                    .map_err(|_| ContractError::InvalidUnitBid {})?;
+                if summarized_bid.coin.amount != required {
+                    return Err(ContractError::UnitBidMismatch { required, deposited: summarized_bid.coin.amount });
+                }
+                UNIT_BIDS.save(deps.storage, bidder.clone(), &UnitBid { units, price })?;
+            }
+            // Bids on a bundle compete with the other bids on exactly the same lots; which
+            // bundles win is only decided on close.
+            (None, None, None) if lots.is_some() => {
+                let lots = bundles::normalize(&config.lots, lots.unwrap_or_default())?;
+                check_starting_price(&config, summarized_bid.coin.amount)?;
+                check_floor(deps.storage, &config, summarized_bid.coin.amount)?;
+                if let Some(previous) = BUNDLE_BIDS.may_load(deps.storage, bidder.clone())? {
+                    if previous != lots {
+                        return Err(ContractError::InvalidLots {});
+                    }
+                }
+                for old_bid in &resp.bids {
+                    if old_bid.address == bidder
+                        || BUNDLE_BIDS.may_load(deps.storage, old_bid.address.clone())?.as_ref() != Some(&lots) {
+                        continue;
+                    }
+                    let required = old_bid.coin.amount.saturating_add(config.min_increment);
+                    if summarized_bid.coin.amount <= required {
+                        return Err(ContractError::BidTooLow { amount: summarized_bid.coin.amount, required });
+                    }
+                    break;
+                }
+                BUNDLE_BIDS.save(deps.storage, bidder.clone(), &lots)?;
+            }
+            (None, None, None) => {
+                if PROXY_BIDS.has(deps.storage, bidder.clone()) {
+                    return Err(ContractError::InvalidProxyBid {});
+                }
+                (_, raised) = outbid(deps.storage, &config, &resp.bids, &bidder, summarized_bid.coin.amount, false)?;
+            }
+            _ => return Err(ContractError::InvalidUnitBid {}),
         }
+        check_caps(deps.as_ref(), &config, &summarized_bid.coin, &value)?;
 
-        BIDS.save(deps.storage, info.sender.clone(), &summarized_bid.coin)?;
+        BIDS.save(deps.storage, bidder.clone(), &summarized_bid.coin)?;
+        match expires {
+            Some(expires) if expires != Expiration::Never {} => EXPIRIES.save(deps.storage, bidder.clone(), &expires)?,
+            _ => EXPIRIES.remove(deps.storage, bidder.clone()),
+        }
+        if *payer != bidder {
+            PAYERS.save(deps.storage, bidder.clone(), payer)?;
+        }
+        let commission = escrow_deposit(deps.storage, &config, &bidder, &deposit)?;
 
-        let mut resp = Response::new()
-            .add_attribute("action", "bid")
-            .add_attribute("sender", info.sender.as_str());
+        let resp = Response::new()
+            .add_event(events::bid_placed(&bidder, payer, &deposit, &summarized_bid.coin, &commission))
+            .add_events(raised)
+            .add_submessages(outbid_hooks(deps.as_ref(), env, &config, &leaders)?);
 
-        if new_bid.commission_as_coin().amount > Uint128::zero() {
-            let bank_msg = BankMsg::Send {
-                to_address: config.owner.to_string(),
-                amount: vec![new_bid.commission_as_coin()],
-            };
-            resp = resp.add_message(bank_msg);
+        Ok(resp)
+    }
+
+    /// Proxy bids are valued in the base denom only, so that the winner can be charged exactly
+    /// their effective bid.
+    pub fn set_max_bid(deps: DepsMut, env: Env, info: MessageInfo, max: Uint128) -> Result<Response, ContractError> {
+        let config = CONFIG.load(deps.storage)?;
+        check_bidder(deps.as_ref(), &config, &info.sender)?;
+        check_payer(deps.storage, &info.sender, &info.sender)?;
+        if config.quantity.is_some() || !config.lots.is_empty() {
+            return Err(ContractError::InvalidProxyBid {});
+        }
+
+        let (deposit, value) = bid_value(&config, &info.funds)?;
+        let mut escrow = ESCROW.may_load(deps.storage, info.sender.clone())?.unwrap_or_default();
+        deposit.iter().for_each(|fund| add_coin(&mut escrow, fund));
+        if escrow.len() != 1 || escrow[0].denom != config.base_denom() || escrow[0].amount != max {
+            return Err(ContractError::InvalidProxyBid {});
         }
 
+        let leaders = query::query_winners(deps.as_ref(), &env)?.winners;
+        let bids = query::query_bids(deps.as_ref(), &env)?.bids;
+        let (effective, raised) = outbid(deps.storage, &config, &bids, &info.sender, max, true)?;
+        check_caps(deps.as_ref(), &config, &escrow[0], &value)?;
+
+        let effective = coin(effective.u128(), config.base_denom());
+        BIDS.save(deps.storage, info.sender.clone(), &effective)?;
+        PROXY_BIDS.save(deps.storage, info.sender.clone(), &max)?;
+        let commission = escrow_deposit(deps.storage, &config, &info.sender, &deposit)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "set_max_bid")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::bid_placed(&info.sender, &info.sender, &deposit, &effective, &commission))
+            .add_events(raised)
+            .add_submessages(outbid_hooks(deps.as_ref(), &env, &config, &leaders)?);
+
         Ok(resp)
     }
 
-    pub fn close(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    /// Notifies the hooks of the `leaders` that are no longer winning. Failing hooks are ignored.
+    fn outbid_hooks(deps: Deps, env: &Env, config: &Config, leaders: &[Allocation]) -> StdResult<Vec<SubMsg>> {
+        let winners = query::query_winners(deps, env)?.winners;
+        let mut hooks = vec![];
+        for leader in leaders {
+            if winners.iter().any(|winner| winner.address == leader.address) {
+                continue;
+            }
+            if let Some(hook) = OUTBID_HOOKS.may_load(deps.storage, leader.address.clone())? {
+                let msg = OutbidHookMsg::Outbid { bidder: leader.address.clone(), commodity: config.commodity.clone() };
+                let wasm_msg = WasmMsg::Execute { contract_addr: hook.to_string(), msg: to_binary(&msg)?, funds: vec![] };
+                let hook = SubMsg::reply_on_error(wasm_msg, OUTBID_HOOK_REPLY_ID).with_gas_limit(OUTBID_HOOK_GAS_LIMIT);
+                hooks.push(hook);
+            }
+        }
+        Ok(hooks)
+    }
+
+    /// Competes a bid of up to `max` against the leading bid. A plain bid is a proxy bid whose max
+    /// is the bid itself. A proxy bid is raised to the lowest bid that outbids the other one, up
+    /// to its max, and ties go to the earlier bid. Returns the effective bid of `bidder` and
+    /// events for the leader's bid if it was raised.
+    fn outbid(
+        storage: &mut dyn Storage,
+        config: &Config,
+        bids: &[Bid],
+        bidder: &Addr,
+        max: Uint128,
+        proxy: bool,
+    ) -> Result<(Uint128, Vec<Event>), ContractError> {
+        let (leader, leading, ceiling) = match bids.first().filter(|bid| !bid.coin.amount.is_zero()) {
+            // The leader only tops up; a proxy's effective bid stays as it is.
+            Some(leader) if leader.address == *bidder => {
+                return Ok((if proxy { leader.coin.amount } else { max }, vec![]));
+            }
+            Some(leader) => {
+                let ceiling = PROXY_BIDS.may_load(storage, leader.address.clone())?.unwrap_or(leader.coin.amount);
+                (Some(leader), leader.coin.amount, ceiling)
+            }
+            // Without a leader the bid opens the auction: a proxy bid at the starting price, or
+            // competing with the owner's deposit like with a plain bid.
+            None => {
+                check_starting_price(config, max)?;
+                match owner_floor(storage, config)? {
+                    Some(floor) => (None, floor, floor),
+                    None => {
+                        let opening = config.starting_price.max(Uint128::one());
+                        return Ok((if proxy { max.min(opening) } else { max }, vec![]));
+                    }
+                }
+            }
+        };
+
+        let required = leading.saturating_add(config.min_increment);
+        if max <= required {
+            return Err(ContractError::BidTooLow { amount: max, required });
+        }
+        // Ties go to the earlier bid.
+        if max == ceiling {
+            return Err(ContractError::BidTooLow { amount: max, required: ceiling });
+        }
+
+        let step = Uint128::one().saturating_add(config.min_increment);
+        let (effective, leader_bid) = if max > ceiling {
+            let effective = if proxy { max.min(ceiling.saturating_add(step)) } else { max };
+            (effective, ceiling)
+        } else {
+            (max, ceiling.min(max.saturating_add(step)))
+        };
+
+        let mut raised = vec![];
+        if let Some(leader) = leader.filter(|_| leader_bid != leading) {
+            let leader_bid = coin(leader_bid.u128(), config.base_denom());
+            BIDS.save(storage, leader.address.clone(), &leader_bid)?;
+            raised.push(events::bid_raised(&leader.address, &leader_bid));
+        }
+        Ok((effective, raised))
+    }
+
+    /// Adds the deposit to the bidder's escrow and charges its commission. Returns the commission.
+    fn escrow_deposit(
+        storage: &mut dyn Storage,
+        config: &Config,
+        bidder: &Addr,
+        deposit: &[Coin],
+    ) -> Result<Vec<Coin>, ContractError> {
+        ESCROW.update(storage, bidder.clone(), |escrow| -> StdResult<_> {
+            let mut escrow = escrow.unwrap_or_default();
+            deposit.iter().for_each(|fund| add_coin(&mut escrow, fund));
+            Ok(escrow)
+        })?;
+
+        // Losing bidders are only charged when configured so; otherwise the winner's commission
+        // is taken on close.
+        let commission: Vec<Coin> = if config.charge_losing_bidders {
+            deposit.iter().map(Commission::commission).collect::<Result<_, _>>()?
+        } else {
+            deposit.iter().map(|fund| coin(0, &fund.denom)).collect()
+        };
+        let charged = non_zero(&commission);
+        if !charged.is_empty() {
+            COMMISSIONS.update(storage, bidder.clone(), |commissions| -> StdResult<_> {
+                let mut commissions = commissions.unwrap_or_default();
+                charged.iter().for_each(|fee| add_coin(&mut commissions, fee));
+                Ok(commissions)
+            })?;
+            FEES.update(storage, |mut fees| -> StdResult<_> {
+                charged.iter().for_each(|fee| add_coin(&mut fees, fee));
+                Ok(fees)
+            })?;
+        }
+        Ok(commission)
+    }
+
+    /// Value of the owner's deposit, if they made one.
+    fn owner_floor(storage: &dyn Storage, config: &Config) -> Result<Option<Uint128>, ContractError> {
+        let deposit = OWNER_DEPOSIT.load(storage)?;
+        if deposit.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(bid_value(config, &deposit)?.1.amount))
+    }
+
+    /// Any bid that can win has to outbid the owner's deposit. Multi-unit bids have to do so with
+    /// their unit price.
+    fn check_floor(storage: &dyn Storage, config: &Config, amount: Uint128) -> Result<(), ContractError> {
+        if let Some(floor) = owner_floor(storage, config)? {
+            let required = floor.saturating_add(config.min_increment);
+            if amount <= required {
+                return Err(ContractError::BidTooLow { amount, required });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_starting_price(config: &Config, amount: Uint128) -> Result<(), ContractError> {
+        if amount < config.starting_price {
+            return Err(ContractError::BelowStartingPrice { amount, starting_price: config.starting_price });
+        }
+        Ok(())
+    }
+
+    fn check_bidder(deps: Deps, config: &Config, bidder: &Addr) -> Result<(), ContractError> {
+        if CLOSED.load(deps.storage)? {
+            return Err(ContractError::AuctionClosed {});
+        }
+        if *bidder == config.owner {
+            return Err(ContractError::OwnerCannotBid {});
+        }
+        check_access(deps, config, bidder)
+    }
+
+    /// A bid is funded by a single payer, who gets its refunds.
+    fn check_payer(storage: &dyn Storage, bidder: &Addr, payer: &Addr) -> Result<(), ContractError> {
+        if ESCROW.has(storage, bidder.clone()) && payer_of(storage, bidder)? != *payer {
+            return Err(ContractError::PayerMismatch {});
+        }
+        Ok(())
+    }
+
+    fn payer_of(storage: &dyn Storage, bidder: &Addr) -> StdResult<Addr> {
+        Ok(PAYERS.may_load(storage, bidder.clone())?.unwrap_or_else(|| bidder.clone()))
+    }
+
+    fn check_access(deps: Deps, config: &Config, bidder: &Addr) -> Result<(), ContractError> {
+        let listed = ACCESS_LIST.has(deps.storage, bidder.clone());
+        let allowed = match config.access_mode {
+            AccessMode::Open => true,
+            AccessMode::Allowlist => listed,
+            AccessMode::Denylist => !listed,
+        };
+        if !allowed {
+            return Err(ContractError::BidderNotAllowed {});
+        }
+
+        if let Some(group) = &config.member_group {
+            let weight = Cw4Contract::new(group.contract.clone()).is_member(&deps.querier, bidder, None)?
+                .ok_or(ContractError::BidderNotAllowed {})?;
+            if weight < group.min_weight.unwrap_or_default() {
+                return Err(ContractError::BidderNotAllowed {});
+            }
+        }
+        Ok(())
+    }
+
+    /// The total is taken from the escrows rather than the balance, which misses what is in the
+    /// vault and holds payouts not withdrawn yet. `deposit` is the value about to be escrowed with
+    /// the bid.
+    fn check_caps(deps: Deps, config: &Config, total: &Coin, deposit: &Coin) -> Result<(), ContractError> {
+        if let Some(cap) = config.max_bid_per_address {
+            if total.amount > cap {
+                return Err(ContractError::BidCapExceeded { cap, attempted: total.amount });
+            }
+        }
+
+        if let Some(cap) = config.max_total_escrow {
+            let mut escrowed = deposit.amount;
+            for item in ESCROW.range(deps.storage, None, None, Order::Ascending) {
+                let (_, escrow) = item?;
+                for held in escrow {
+                    let accepted = config.denoms.iter().find(|accepted| accepted.denom == held.denom)
+                        .ok_or(ContractError::UnsupportedDenom { denom: held.denom })?;
+                    escrowed = escrowed.checked_add(weighted(held.amount, accepted.weight)?)
+                        .map_err(|_| ContractError::Overflow {})?;
+                }
+            }
+            if escrowed > cap {
+                return Err(ContractError::BidCapExceeded { cap, attempted: escrowed });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn close(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
         let config = CONFIG.load(deps.storage)?;
         if info.sender != config.owner {
             return Err(ContractError::Unauthorized {});
         }
+        if CLOSED.load(deps.storage)? {
+            return Err(ContractError::AuctionClosed {});
+        }
 
         let mut resp = Response::new()
             .add_attribute("action", "close")
             .add_attribute("sender", info.sender.as_str());
 
-        let result = query::query_bids(deps.as_ref())?;
-        let highest_bid = result.bids.first();
-        if highest_bid.is_some() && highest_bid.unwrap().coin.amount != Uint128::zero() {
-            let bank_msg = BankMsg::Send {
-                to_address: info.sender.to_string(),
-                amount: vec![highest_bid.unwrap().amount_as_coin()],
-            };
+        let winners = query::query_winners(deps.as_ref(), &env)?.winners;
+        CLOSED.save(deps.storage, &true)?;
+
+        let mut fees = FEES.load(deps.storage)?;
+        let mut payout = vec![];
 
-            resp = resp.add_message(bank_msg);
+        // Everything comes out of the vault ahead of the transfers below. The yield goes to the
+        // owner or, shared by the bids still escrowed, back with the refunds.
+        let mut yields = vec![];
+        if let Some(vault) = &config.vault {
+            let (withdrawal, accrued) = vault::withdraw_all(deps.storage, &deps.querier, &env, &vault.contract)?;
+            resp = resp.add_messages(withdrawal);
+            let mut to_owner = accrued.clone();
+            if vault.yield_recipient == YieldRecipient::Bidders {
+                yields = vault::yield_shares(deps.storage, &accrued)?;
+                for (_, share) in &yields {
+                    share.iter().try_for_each(|amount| sub_coin(&mut to_owner, amount))?;
+                }
+            }
+            to_owner.iter().for_each(|amount| add_coin(&mut payout, amount));
+            resp = resp.add_event(events::vault_yield(&accrued, &non_zero(&to_owner)));
         }
 
-        Ok(resp)
+        let mut sales = vec![];
+        let mut winning_value = Uint128::zero();
+        let mut hooks = vec![];
+        for (id, winner) in (SETTLEMENT_HOOK_REPLY_ID..).zip(&winners) {
+            let paid = winner_payment(deps.storage, &config, winner);
+            let payer = payer_of(deps.storage, &winner.address)?;
+            let escrow = ESCROW.may_load(deps.storage, winner.address.clone())?.unwrap_or_default();
+            paid_amounts(&escrow, paid.as_ref()).iter().for_each(|sold| add_coin(&mut sales, sold));
+            let (owed, mut refund) = settle(deps.storage, &mut fees, &winner.address, paid.as_ref())?;
+            if let Some((_, share)) = yields.iter().find(|(bidder, _)| *bidder == winner.address) {
+                share.iter().for_each(|amount| add_coin(&mut refund, amount));
+            }
+            owed.iter().for_each(|owed| add_coin(&mut payout, owed));
+            let price = winner.price * Uint128::from(winner.units);
+            winning_value += price;
+            WINNERS.save(deps.storage, winner.address.clone(), winner)?;
+
+            if let Some(hook) = &config.settlement_hook {
+                let msg = SettlementHookMsg::AuctionSettled {
+                    winner: winner.address.clone(),
+                    price: coin(price.u128(), config.base_denom()),
+                    commodity: config.commodity.clone(),
+                };
+                let wasm_msg = WasmMsg::Execute { contract_addr: hook.to_string(), msg: to_binary(&msg)?, funds: vec![] };
+                hooks.push(SubMsg::reply_always(wasm_msg, id).with_gas_limit(SETTLEMENT_HOOK_GAS_LIMIT));
+                SETTLEMENT_HOOKS.save(deps.storage, id, &winner.address)?;
+            }
+
+            if config.quantity.is_some() {
+                resp = resp.add_event(events::units_allocated(winner, &refund));
+            }
+            if !winner.lots.is_empty() {
+                resp = resp.add_event(events::lots_settled(winner, &owed));
+            }
+            credit(deps.storage, &payer, &refund)?;
+        }
+        FEES.save(deps.storage, &non_zero(&fees))?;
+
+        // Losing bids get their share with the rest of their escrow when they retract.
+        for (bidder, share) in yields {
+            if !WINNERS.has(deps.storage, bidder.clone()) {
+                ESCROW.update(deps.storage, bidder, |escrow| -> StdResult<_> {
+                    let mut escrow = escrow.unwrap_or_default();
+                    share.iter().for_each(|amount| add_coin(&mut escrow, amount));
+                    Ok(escrow)
+                })?;
+            }
+        }
+
+        for (recipient, amount) in royalties(&config, &sales) {
+            amount.iter().try_for_each(|royalty| sub_coin(&mut payout, royalty))?;
+            credit(deps.storage, &recipient, &amount)?;
+            resp = resp.add_event(events::royalty_paid(&recipient, &amount));
+        }
+
+        // The owner's deposit only set a floor, so it goes back as it is.
+        OWNER_DEPOSIT.load(deps.storage)?.iter().for_each(|deposit| add_coin(&mut payout, deposit));
+        OWNER_DEPOSIT.save(deps.storage, &vec![])?;
+        let payout = non_zero(&payout);
+
+        let winner_addresses: Vec<_> = winners.iter().map(|winner| winner.address.clone()).collect();
+        resp = resp.add_event(events::auction_closed(
+            &config.owner,
+            &winner_addresses,
+            &coin(winning_value.u128(), config.base_denom()),
+            &payout,
+        ));
+
+        credit(deps.storage, &info.sender, &payout)?;
+
+        Ok(resp.add_submessages(hooks))
     }
 
-    pub fn retract(deps: DepsMut, _env: Env, info: MessageInfo, receiver: Option<String>) -> Result<Response, ContractError> {
-        let receiver = receiver.unwrap_or_else(|| info.sender.to_string());
-        let validated_receiver = deps.api.addr_validate(&receiver)?;
+    /// Charges whatever part of the winner's commission was not charged while bidding, gives back
+    /// what was charged beyond it and clears their escrow. Returns what is owed to the owner and
+    /// what is refunded to the winner.
+    fn settle(
+        storage: &mut dyn Storage,
+        fees: &mut Vec<Coin>,
+        winner: &Addr,
+        paid: Option<&Coin>,
+    ) -> Result<(Vec<Coin>, Vec<Coin>), ContractError> {
+        let escrow = ESCROW.may_load(storage, winner.clone())?.unwrap_or_default();
+        let charged = COMMISSIONS.may_load(storage, winner.clone())?.unwrap_or_default();
+        let Settlement { owed, refund, missing_fees: missing, excess_fees: excess } = settlement(&escrow, &charged, paid)?;
+        missing.iter().for_each(|fee| add_coin(fees, fee));
+        excess.iter().try_for_each(|fee| sub_coin(fees, fee))?;
 
-        let result = query::query_bids(deps.as_ref())?;
-        let split: Option<(&Bid, &[Bid])> = result.bids.split_first();
-        if split.is_none() {
-            return Err(ContractError::NoRectractableBid {});
+        ESCROW.remove(storage, winner.clone());
+        COMMISSIONS.remove(storage, winner.clone());
+        EXPIRIES.remove(storage, winner.clone());
+        PAYERS.remove(storage, winner.clone());
+        Ok((owed, refund))
+    }
+
+    /// Each royalty recipient's share of `sold`, rounded down so that the dust stays with the owner.
+    fn royalties(config: &Config, sold: &[Coin]) -> Vec<(Addr, Vec<Coin>)> {
+        config.royalties.iter()
+            .map(|royalty| {
+                let amounts: Vec<_> = sold.iter()
+                    .map(|sold| coin((sold.amount * royalty.share).u128(), &sold.denom))
+                    .collect();
+                (royalty.address.clone(), non_zero(&amounts))
+            })
+            .filter(|(_, amounts)| !amounts.is_empty())
+            .collect()
+    }
+
+    /// The payer, the beneficiary and the beneficiary's operators can retract a bid, but only the
+    /// payer can have the refund sent anywhere but back to the payer.
+    pub fn retract(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        receiver: Option<String>,
+        on_behalf_of: Option<String>,
+    ) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let bidder = match on_behalf_of {
+            Some(beneficiary) => deps.api.addr_validate(&beneficiary)?,
+            None => info.sender.clone(),
+        };
+        let payer = payer_of(deps.storage, &bidder)?;
+        let validated_receiver = match receiver {
+            Some(receiver) => deps.api.addr_validate(&receiver)?,
+            None => payer.clone(),
+        };
+        let acting_for_bidder = info.sender == bidder || is_operator(deps.storage, &env, &bidder, &info.sender)?;
+        if info.sender != payer && (!acting_for_bidder || validated_receiver != payer) {
+            return Err(ContractError::Unauthorized {});
         }
 
-        let bid: &Bid = split.unwrap().1.iter().find(|bid| {
-            bid.address == info.sender
-        }).ok_or(ContractError::NoRectractableBid {})?;
+        // Winning bids are locked: before close the current winners, afterwards the settled ones.
+        let winning = if CLOSED.load(deps.storage)? {
+            WINNERS.has(deps.storage, bidder.clone())
+        } else {
+            query::query_winners(deps.as_ref(), &env)?.winners.iter().any(|winner| winner.address == bidder)
+        };
+        if winning {
+            return Err(ContractError::NoRectractableBid {});
+        }
 
-        if bid.coin.amount == Uint128::zero() {
+        let bid = BIDS.may_load(deps.storage, bidder.clone())?
+            .ok_or(ContractError::NoRectractableBid {})?;
+        if bid.amount == Uint128::zero() {
             return Err(ContractError::NoRectractableBid {});
         }
 
-        let mut resp = Response::new()
+        let (_, refund) = remove_bid(deps.storage, &bidder)?;
+        credit(deps.storage, &validated_receiver, &refund)?;
+
+        let resp = Response::new()
             .add_attribute("action", "retract")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::bid_retracted(&bidder, &validated_receiver, &refund));
+
+        Ok(resp)
+    }
+
+    /// Withdraws part of the sender's escrow, refunded to whoever paid for the bid. Commission
+    /// charged on the withdrawn part is kept. The bid has to stay above the owner's deposit, and
+    /// the leader above the runner-up, by more than the minimum increment. Withdrawing everything
+    /// takes a retract.
+    pub fn decrease_bid(deps: DepsMut, env: Env, info: MessageInfo, amount: Vec<Coin>) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let config = CONFIG.load(deps.storage)?;
+        if CLOSED.load(deps.storage)? {
+            return Err(ContractError::AuctionClosed {});
+        }
+        if info.sender == config.owner {
+            return Err(ContractError::OwnerCannotBid {});
+        }
+        if config.quantity.is_some() {
+            return Err(ContractError::InvalidUnitBid {});
+        }
+        if !config.lots.is_empty() {
+            return Err(ContractError::InvalidLots {});
+        }
+        if PROXY_BIDS.has(deps.storage, info.sender.clone()) {
+            return Err(ContractError::InvalidProxyBid {});
+        }
+
+        let (withdrawal, value) = bid_value(&config, &amount)?;
+        let bid = BIDS.may_load(deps.storage, info.sender.clone())?
+            .ok_or(ContractError::NoRectractableBid {})?;
+        let total = coin(bid.amount.saturating_sub(value.amount).u128(), &bid.denom);
+        if total.amount.is_zero() {
+            return Err(ContractError::DecreaseToZero {});
+        }
+        check_starting_price(&config, total.amount)?;
+        check_floor(deps.storage, &config, total.amount)?;
+
+        let bids = query::query_bids(deps.as_ref(), &env)?.bids;
+        if bids.first().map_or(false, |leader| leader.address == info.sender) {
+            let runner_up = bids.get(1).map(|bid| bid.coin.amount).unwrap_or_default();
+            let required = runner_up.saturating_add(config.min_increment);
+            if total.amount <= required {
+                return Err(ContractError::BidTooLow { amount: total.amount, required });
+            }
+        }
+
+        // What was charged beyond the commission on the remaining escrow comes out of the refund,
+        // so that the escrow always covers its commission.
+        let mut escrow = ESCROW.load(deps.storage, info.sender.clone())?;
+        let mut charged = COMMISSIONS.may_load(deps.storage, info.sender.clone())?.unwrap_or_default();
+        let mut refund = vec![];
+        for fund in &withdrawal {
+            sub_coin(&mut escrow, fund)?;
+            let remaining = escrow.iter().find(|coin| coin.denom == fund.denom).cloned()
+                .unwrap_or_else(|| coin(0, &fund.denom));
+            let kept = match charged.iter_mut().find(|fee| fee.denom == fund.denom) {
+                Some(fee) => {
+                    let limit = remaining.commission()?.amount;
+                    let kept = fee.amount.saturating_sub(limit);
+                    fee.amount -= kept;
+                    kept
+                }
+                None => Uint128::zero(),
+            };
+            add_coin(&mut refund, &coin((fund.amount - kept).u128(), &fund.denom));
+        }
+        let refund = non_zero(&refund);
+
+        BIDS.save(deps.storage, info.sender.clone(), &total)?;
+        ESCROW.save(deps.storage, info.sender.clone(), &non_zero(&escrow))?;
+        COMMISSIONS.save(deps.storage, info.sender.clone(), &non_zero(&charged))?;
+
+        let payer = payer_of(deps.storage, &info.sender)?;
+        credit(deps.storage, &payer, &refund)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "decrease_bid")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::bid_decreased(&info.sender, &withdrawal, &total, &refund));
+
+        Ok(resp)
+    }
+
+    /// Refunds up to `limit` expired bids. Anyone can prune.
+    pub fn prune_expired(deps: DepsMut, env: Env, info: MessageInfo, limit: Option<u32>) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let limit = limit.unwrap_or(query::DEFAULT_LIMIT).min(query::MAX_LIMIT) as usize;
+        let mut expired = vec![];
+        for item in EXPIRIES.range(deps.storage, None, None, Order::Ascending) {
+            let (address, expires) = item?;
+            if expired.len() == limit {
+                break;
+            }
+            if expires.is_expired(&env.block) {
+                expired.push(address);
+            }
+        }
+
+        let mut resp = Response::new()
+            .add_attribute("action", "prune_expired")
             .add_attribute("sender", info.sender.as_str());
 
-        let bank_msg = BankMsg::Send {
-            to_address: validated_receiver.to_string(),
-            amount: vec![coin(bid.amount_as_coin().amount.u128(), DENOM)],
+        for bidder in expired {
+            let (payer, refund) = remove_bid(deps.storage, &bidder)?;
+            credit(deps.storage, &payer, &refund)?;
+            resp = resp.add_event(events::bid_expired(&bidder, &refund));
+        }
+
+        Ok(resp)
+    }
+
+    /// Removes everything kept for a bid. Returns who paid for it and its escrow less the
+    /// commission charged.
+    fn remove_bid(storage: &mut dyn Storage, bidder: &Addr) -> Result<(Addr, Vec<Coin>), ContractError> {
+        let payer = payer_of(storage, bidder)?;
+        let mut refund = ESCROW.load(storage, bidder.clone())?;
+        let charged = COMMISSIONS.may_load(storage, bidder.clone())?.unwrap_or_default();
+        charged.iter().try_for_each(|fee| sub_coin(&mut refund, fee))?;
+
+        BIDS.remove(storage, bidder.clone());
+        ESCROW.remove(storage, bidder.clone());
+        COMMISSIONS.remove(storage, bidder.clone());
+        UNIT_BIDS.remove(storage, bidder.clone());
+        BUNDLE_BIDS.remove(storage, bidder.clone());
+        PROXY_BIDS.remove(storage, bidder.clone());
+        EXPIRIES.remove(storage, bidder.clone());
+        PAYERS.remove(storage, bidder.clone());
+        Ok((payer, non_zero(&refund)))
+    }
+
+    /// Approving an operator again replaces its expiration and limit; funds sent along are added to
+    /// the reserve the operator raises bids from.
+    pub fn approve_operator(
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        operator: String,
+        expires: Option<Expiration>,
+        max_amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        let config = CONFIG.load(deps.storage)?;
+        let operator = deps.api.addr_validate(&operator)?;
+        let deposit = if info.funds.is_empty() {
+            vec![]
+        } else {
+            bid_value(&config, &info.funds)?.0
+        };
+
+        let key = (info.sender.clone(), operator.clone());
+        let mut reserve = OPERATORS.may_load(deps.storage, key.clone())?
+            .map(|approval| approval.reserve)
+            .unwrap_or_default();
+        deposit.iter().for_each(|fund| add_coin(&mut reserve, fund));
+        let approval = Approval { expires: expires.unwrap_or_default(), max_amount, reserve };
+        OPERATORS.save(deps.storage, key, &approval)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "approve_operator")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::operator_approved(&info.sender, &operator, &approval));
+
+        Ok(resp)
+    }
+
+    /// Returns what is left of the operator's reserve to the granter.
+    pub fn revoke_operator(deps: DepsMut, _env: Env, info: MessageInfo, operator: String) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let operator = deps.api.addr_validate(&operator)?;
+        let key = (info.sender.clone(), operator.clone());
+        let approval = OPERATORS.may_load(deps.storage, key.clone())?
+            .ok_or(ContractError::OperatorNotApproved {})?;
+        OPERATORS.remove(deps.storage, key);
+
+        credit(deps.storage, &info.sender, &approval.reserve)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "revoke_operator")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::operator_revoked(&info.sender, &operator, &approval.reserve));
+
+        Ok(resp)
+    }
+
+    /// Raises the granter's bid by `amount` taken from the operator's reserve. The granter's whole
+    /// bid cannot exceed the approval's `max_amount`.
+    pub fn raise_bid(
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        granter: String,
+        amount: Vec<Coin>,
+    ) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let granter = deps.api.addr_validate(&granter)?;
+        let key = (granter.clone(), info.sender.clone());
+        let mut approval = OPERATORS.may_load(deps.storage, key.clone())?
+            .filter(|approval| !approval.expires.is_expired(&env.block))
+            .ok_or(ContractError::OperatorNotApproved {})?;
+        amount.iter().try_for_each(|fund| sub_coin(&mut approval.reserve, fund))?;
+        approval.reserve = non_zero(&approval.reserve);
+        OPERATORS.save(deps.storage, key, &approval)?;
+
+        let expires = EXPIRIES.may_load(deps.storage, granter.clone())?;
+        let resp = place_bid(deps.branch(), &env, &granter, granter.clone(), &amount, None, None, None, expires)?
+            .add_attribute("action", "raise_bid")
+            .add_attribute("sender", info.sender.as_str());
+
+        let total = BIDS.load(deps.storage, granter)?.amount;
+        if total > approval.max_amount {
+            return Err(ContractError::BidCapExceeded { cap: approval.max_amount, attempted: total });
+        }
+
+        Ok(resp)
+    }
+
+    pub fn register_outbid_hook(
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        contract: String,
+    ) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let hook = deps.api.addr_validate(&contract)?;
+        OUTBID_HOOKS.save(deps.storage, info.sender.clone(), &hook)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "register_outbid_hook")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::outbid_hook_registered(&info.sender, &hook));
+
+        Ok(resp)
+    }
+
+    fn is_operator(storage: &dyn Storage, env: &Env, granter: &Addr, operator: &Addr) -> StdResult<bool> {
+        let approval = OPERATORS.may_load(storage, (granter.clone(), operator.clone()))?;
+        Ok(approval.map_or(false, |approval| !approval.expires.is_expired(&env.block)))
+    }
+
+    pub fn claim_fees(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let config = CONFIG.load(deps.storage)?;
+        if info.sender != config.fee_recipient {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        // Commission charged on bids still open is refunded in part if they win, so it is only
+        // claimable once the auction closed.
+        let mut fees = FEES.load(deps.storage)?;
+        let mut pending = vec![];
+        if !CLOSED.load(deps.storage)? {
+            for item in COMMISSIONS.range(deps.storage, None, None, Order::Ascending) {
+                let (_, charged) = item?;
+                charged.iter().for_each(|fee| add_coin(&mut pending, fee));
+            }
+            pending.iter().try_for_each(|fee| sub_coin(&mut fees, fee))?;
+        }
+        let fees = non_zero(&fees);
+        if fees.is_empty() {
+            return Err(ContractError::NoFeesToClaim {});
+        }
+        FEES.save(deps.storage, &non_zero(&pending))?;
+        credit(deps.storage, &config.fee_recipient, &fees)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "claim_fees")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::fees_claimed(&config.fee_recipient, &fees));
+
+        Ok(resp)
+    }
+
+    /// Sends everything credited to the sender to `receiver`, which defaults to the sender.
+    pub fn withdraw(deps: DepsMut, _env: Env, info: MessageInfo, receiver: Option<String>) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let receiver = match receiver {
+            Some(receiver) => deps.api.addr_validate(&receiver)?,
+            None => info.sender.clone(),
         };
-        resp = resp.add_message(bank_msg);
+        let amount = CLAIMABLE.may_load(deps.storage, info.sender.clone())?.unwrap_or_default();
+        if amount.is_empty() {
+            return Err(ContractError::NothingToWithdraw {});
+        }
+        CLAIMABLE.remove(deps.storage, info.sender.clone());
+
+        let resp = Response::new()
+            .add_attribute("action", "withdraw")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::withdrawn(&info.sender, &receiver, &amount))
+            .add_message(BankMsg::Send {
+                to_address: receiver.to_string(),
+                amount,
+            });
+
+        Ok(resp)
+    }
+
+    /// Payouts are only credited here and withdrawn separately, so that a recipient that cannot
+    /// receive funds does not block anyone else.
+    fn credit(storage: &mut dyn Storage, address: &Addr, amount: &[Coin]) -> StdResult<()> {
+        if amount.is_empty() {
+            return Ok(());
+        }
+        CLAIMABLE.update(storage, address.clone(), |claimable| -> StdResult<_> {
+            let mut claimable = claimable.unwrap_or_default();
+            amount.iter().for_each(|amount| add_coin(&mut claimable, amount));
+            Ok(claimable)
+        })?;
+        Ok(())
+    }
+
+    pub fn update_access_list(
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        add: Vec<String>,
+        remove: Vec<String>,
+    ) -> Result<Response, ContractError> {
+        nonpayable(&info)?;
+        let config = CONFIG.load(deps.storage)?;
+        if info.sender != config.owner {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        let add = add.iter()
+            .map(|address| deps.api.addr_validate(address))
+            .collect::<StdResult<Vec<_>>>()?;
+        let remove = remove.iter()
+            .map(|address| deps.api.addr_validate(address))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for address in &add {
+            ACCESS_LIST.save(deps.storage, address.clone(), &Empty {})?;
+        }
+        for address in &remove {
+            ACCESS_LIST.remove(deps.storage, address.clone());
+        }
+
+        let resp = Response::new()
+            .add_attribute("action", "update_access_list")
+            .add_attribute("sender", info.sender.as_str())
+            .add_event(events::access_list_updated(&add, &remove));
 
         Ok(resp)
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query::query_config(deps)?),
-        QueryMsg::Bids {} => to_binary(&query::query_bids(deps)?),
+        QueryMsg::Bids {} => to_binary(&query::query_bids(deps, &env)?),
+        QueryMsg::Fees {} => to_binary(&query::query_fees(deps)?),
+        QueryMsg::Escrow { address } => to_binary(&query::query_escrow(deps, address)?),
+        QueryMsg::AccessList { start_after, limit } => to_binary(&query::query_access_list(deps, start_after, limit)?),
+        QueryMsg::Winners {} => to_binary(&query::query_winners(deps, &env)?),
+        QueryMsg::Approval { granter, operator } => to_binary(&query::query_approval(deps, granter, operator)?),
+        QueryMsg::Accounting {} => to_binary(&query::query_accounting(deps, &env)?),
+        QueryMsg::FailedSettlements {} => to_binary(&query::query_failed_settlements(deps)?),
+        QueryMsg::Claimable { address } => to_binary(&query::query_claimable(deps, address)?),
     }
 }
 
 
 pub mod query {
-    use cosmwasm_std::{Deps, StdResult};
+    use std::cmp::Reverse;
+
+    use cosmwasm_std::{Addr, Deps, Env, Order, StdError, StdResult};
+    use cw_storage_plus::Bound;
+
+    use crate::contract::{add_coin, non_zero, Settlement, settlement, sub_coin, winner_payment};
+    use crate::msg::{
+        AccessListResponse, AccountingResponse, ApprovalResponse, Bid, BidsResponse, ClaimableResponse, ConfigResponse,
+        EscrowResponse, FailedSettlement, FailedSettlementsResponse, FeesResponse, WinnersResponse,
+    };
+    use crate::bundles;
+    use crate::state::{
+        ACCESS_LIST, Allocation, BIDS, BUNDLE_BIDS, CLAIMABLE, CLOSED, COMMISSIONS, CONFIG, ESCROW, EXPIRIES,
+        FAILED_SETTLEMENTS, FEES, OPERATORS, OWNER_DEPOSIT, PROXY_BIDS, UNIT_BIDS, VAULT_PRINCIPAL, WINNERS,
+    };
 
-    use crate::msg::{Bid, BidsResponse, ConfigResponse};
-    use crate::state::{BIDS, CONFIG};
+    pub(crate) const DEFAULT_LIMIT: u32 = 10;
+    pub(crate) const MAX_LIMIT: u32 = 30;
 
     pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         let config = CONFIG.load(deps.storage)?;
         Ok(ConfigResponse { config })
     }
 
-    pub fn query_bids(deps: Deps) -> StdResult<BidsResponse> {
-        let mut bids: Vec<Bid> = BIDS
-            .range(deps.storage, None, None, cosmwasm_std::Order::Descending)
-            .map(|item| {
-                let (address, coin) = item?;
-                Ok(Bid {
+    /// Expired bids are left out.
+    pub fn query_bids(deps: Deps, env: &Env) -> StdResult<BidsResponse> {
+        let mut bids: Vec<Bid> = vec![];
+        for item in BIDS.range(deps.storage, None, None, Order::Descending) {
+            let (address, coin) = item?;
+            if !is_expired(deps, env, &address)? {
+                bids.push(Bid {
                     address,
                     coin,
-                })
-            })
-            .collect::<StdResult<_>>()?;
+                });
+            }
+        }
         bids.sort_by(|a, b| {
             b.coin.amount.cmp(&a.coin.amount)
         });
         Ok(BidsResponse { bids })
     }
+
+    /// The settled winners once closed, otherwise who would win if the auction closed now.
+    ///
+    /// In a multi-unit auction units go to the highest unit prices first, ties going to the lower
+    /// address, and every winner pays the lowest winning unit price. With bundled lots the winners
+    /// are the bids on disjoint lots that bring in the most.
+    pub fn query_winners(deps: Deps, env: &Env) -> StdResult<WinnersResponse> {
+        if CLOSED.load(deps.storage)? {
+            let winners = WINNERS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, winner)| winner))
+                .collect::<StdResult<_>>()?;
+            return Ok(WinnersResponse { winners });
+        }
+
+        let config = CONFIG.load(deps.storage)?;
+        if !config.lots.is_empty() {
+            let mut bundle_bids = vec![];
+            for bid in query_bids(deps, env)?.bids {
+                if let Some(lots) = BUNDLE_BIDS.may_load(deps.storage, bid.address.clone())? {
+                    bundle_bids.push(Allocation { address: bid.address, units: 1, price: bid.coin.amount, lots });
+                }
+            }
+            let winners = bundles::best_bundles(&config.lots, bundle_bids);
+            return Ok(WinnersResponse { winners });
+        }
+
+        let quantity = match config.quantity {
+            Some(quantity) => quantity,
+            None => {
+                let winners = query_bids(deps, env)?.bids.into_iter()
+                    .take(1)
+                    .filter(|bid| !bid.coin.amount.is_zero())
+                    .map(|bid| Allocation { address: bid.address, units: 1, price: bid.coin.amount, lots: vec![] })
+                    .collect();
+                return Ok(WinnersResponse { winners });
+            }
+        };
+
+        let mut unit_bids = vec![];
+        for item in UNIT_BIDS.range(deps.storage, None, None, Order::Ascending) {
+            let (address, bid) = item?;
+            if !is_expired(deps, env, &address)? {
+                unit_bids.push((address, bid));
+            }
+        }
+        unit_bids.sort_by_key(|(_, bid)| Reverse(bid.price));
+
+        let mut remaining = quantity;
+        let mut winners: Vec<Allocation> = vec![];
+        for (address, bid) in unit_bids {
+            if remaining == 0 {
+                break;
+            }
+            let units = bid.units.min(remaining);
+            remaining -= units;
+            winners.push(Allocation { address, units, price: bid.price, lots: vec![] });
+        }
+
+        if let Some(clearing_price) = winners.last().map(|winner| winner.price) {
+            winners.iter_mut().for_each(|winner| winner.price = clearing_price);
+        }
+        Ok(WinnersResponse { winners })
+    }
+
+    fn is_expired(deps: Deps, env: &Env, address: &Addr) -> StdResult<bool> {
+        let expires = EXPIRIES.may_load(deps.storage, address.clone())?;
+        Ok(expires.map_or(false, |expires| expires.is_expired(&env.block)))
+    }
+
+    pub fn query_fees(deps: Deps) -> StdResult<FeesResponse> {
+        let config = CONFIG.load(deps.storage)?;
+        let fees = FEES.load(deps.storage)?;
+        Ok(FeesResponse { recipient: config.fee_recipient, fees })
+    }
+
+    /// A proxy bidder's escrow would give their max away, so their effective bid is shown instead.
+    pub fn query_escrow(deps: Deps, address: String) -> StdResult<EscrowResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let coins = if address == CONFIG.load(deps.storage)?.owner {
+            OWNER_DEPOSIT.load(deps.storage)?
+        } else if PROXY_BIDS.has(deps.storage, address.clone()) && ESCROW.has(deps.storage, address.clone()) {
+            BIDS.may_load(deps.storage, address.clone())?.into_iter().collect()
+        } else {
+            ESCROW.may_load(deps.storage, address.clone())?.unwrap_or_default()
+        };
+        Ok(EscrowResponse { address, coins })
+    }
+
+    pub fn query_claimable(deps: Deps, address: String) -> StdResult<ClaimableResponse> {
+        let address = deps.api.addr_validate(&address)?;
+        let amount = CLAIMABLE.may_load(deps.storage, address.clone())?.unwrap_or_default();
+        Ok(ClaimableResponse { address, amount })
+    }
+
+    pub fn query_approval(deps: Deps, granter: String, operator: String) -> StdResult<ApprovalResponse> {
+        let granter = deps.api.addr_validate(&granter)?;
+        let operator = deps.api.addr_validate(&operator)?;
+        let approval = OPERATORS.may_load(deps.storage, (granter, operator))?;
+        Ok(ApprovalResponse { approval })
+    }
+
+    /// Everything the contract owes: to the owner and the fee recipient if the auction closed now,
+    /// and to the bidders and operators. Together it never exceeds the balance.
+    pub fn query_accounting(deps: Deps, env: &Env) -> StdResult<AccountingResponse> {
+        let config = CONFIG.load(deps.storage)?;
+        let winners = if CLOSED.load(deps.storage)? {
+            vec![]
+        } else {
+            query_winners(deps, env)?.winners
+        };
+
+        let mut total_escrow = vec![];
+        let mut refundable = vec![];
+        let mut payout_owed = OWNER_DEPOSIT.load(deps.storage)?;
+        let mut fees = FEES.load(deps.storage)?;
+        for item in ESCROW.range(deps.storage, None, None, Order::Ascending) {
+            let (address, escrow) = item?;
+            escrow.iter().for_each(|deposit| add_coin(&mut total_escrow, deposit));
+            let charged = COMMISSIONS.may_load(deps.storage, address.clone())?.unwrap_or_default();
+            match winners.iter().find(|winner| winner.address == address) {
+                Some(winner) => {
+                    let paid = winner_payment(deps.storage, &config, winner);
+                    let Settlement { owed, refund, missing_fees: missing, excess_fees: excess } =
+                        settlement(&escrow, &charged, paid.as_ref())
+                        .map_err(|err| StdError::generic_err(err.to_string()))?;
+                    owed.iter().for_each(|owed| add_coin(&mut payout_owed, owed));
+                    refund.iter().for_each(|refund| add_coin(&mut refundable, refund));
+                    missing.iter().for_each(|fee| add_coin(&mut fees, fee));
+                    excess.iter().try_for_each(|fee| sub_coin(&mut fees, fee))?;
+                }
+                None => {
+                    let mut refund = escrow;
+                    charged.iter().try_for_each(|fee| sub_coin(&mut refund, fee))?;
+                    refund.iter().for_each(|refund| add_coin(&mut refundable, refund));
+                }
+            }
+        }
+
+        let mut reserves = vec![];
+        for item in OPERATORS.range(deps.storage, None, None, Order::Ascending) {
+            let (_, approval) = item?;
+            approval.reserve.iter().for_each(|reserve| add_coin(&mut reserves, reserve));
+        }
+
+        let mut claimable = vec![];
+        for item in CLAIMABLE.range(deps.storage, None, None, Order::Ascending) {
+            let (_, amount) = item?;
+            amount.iter().for_each(|amount| add_coin(&mut claimable, amount));
+        }
+
+        let balance = deps.querier.query_all_balances(&env.contract.address)?;
+        Ok(AccountingResponse {
+            total_escrow: non_zero(&total_escrow),
+            fees: non_zero(&fees),
+            refundable: non_zero(&refundable),
+            payout_owed: non_zero(&payout_owed),
+            reserves: non_zero(&reserves),
+            claimable: non_zero(&claimable),
+            balance,
+            vault: non_zero(&VAULT_PRINCIPAL.may_load(deps.storage)?.unwrap_or_default()),
+        })
+    }
+
+    pub fn query_access_list(deps: Deps, start_after: Option<String>, limit: Option<u32>) -> StdResult<AccessListResponse> {
+        let config = CONFIG.load(deps.storage)?;
+        let start_after = start_after.map(|address| deps.api.addr_validate(&address)).transpose()?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+        let addresses = ACCESS_LIST
+            .keys(deps.storage, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<_>>()?;
+        Ok(AccessListResponse { mode: config.access_mode, addresses })
+    }
+
+    pub fn query_failed_settlements(deps: Deps) -> StdResult<FailedSettlementsResponse> {
+        let failures = FAILED_SETTLEMENTS
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(winner, error)| FailedSettlement { winner, error }))
+            .collect::<StdResult<_>>()?;
+        Ok(FailedSettlementsResponse { failures })
+    }
 }
 
+pub mod reply {
+    use cosmwasm_std::{DepsMut, Response, SubMsgResult};
 
+    use crate::{ContractError, events};
+    use crate::state::{FAILED_SETTLEMENTS, SETTLEMENT_HOOKS};
+
+    /// Outbid notifications are best effort, so a failing hook is only reported.
+    pub fn outbid_hook(result: SubMsgResult) -> Result<Response, ContractError> {
+        let mut resp = Response::new();
+        if let SubMsgResult::Err(error) = result {
+            resp = resp.add_event(events::outbid_hook_failed(&error));
+        }
+        Ok(resp)
+    }
+
+    /// Records a failed settlement hook; the close it was sent from goes through regardless.
+    pub fn settlement_hook(deps: DepsMut, id: u64, result: SubMsgResult) -> Result<Response, ContractError> {
+        let winner = SETTLEMENT_HOOKS.load(deps.storage, id)?;
+        SETTLEMENT_HOOKS.remove(deps.storage, id);
+
+        let mut resp = Response::new();
+        if let SubMsgResult::Err(error) = result {
+            FAILED_SETTLEMENTS.save(deps.storage, winner.clone(), &error)?;
+            resp = resp.add_event(events::settlement_hook_failed(&winner, &error));
+        }
+        Ok(resp)
+    }
+}
+
+/// What a winner pays out of their escrow. A multi-unit winner pays for the allocated units only
+/// and a proxy bidder their effective bid; `None` means the whole escrow.
+pub(crate) fn winner_payment(storage: &dyn Storage, config: &Config, winner: &Allocation) -> Option<Coin> {
+    if config.quantity.is_some() || PROXY_BIDS.has(storage, winner.address.clone()) {
+        Some(coin((winner.price * Uint128::from(winner.units)).u128(), config.base_denom()))
+    } else {
+        None
+    }
+}
+
+/// What a winner pays out of each coin of their escrow. `paid` defaults to the whole escrow.
+pub(crate) fn paid_amounts(escrow: &[Coin], paid: Option<&Coin>) -> Vec<Coin> {
+    escrow.iter().map(|deposit| {
+        let amount = match paid {
+            Some(paid) if paid.denom == deposit.denom => paid.amount.min(deposit.amount),
+            Some(_) => Uint128::zero(),
+            None => deposit.amount,
+        };
+        coin(amount.u128(), &deposit.denom)
+    }).collect()
+}
+
+/// How a winner's escrow is split on close.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Settlement {
+    /// Owed to the owner.
+    pub owed: Vec<Coin>,
+    pub refund: Vec<Coin>,
+    /// Commission still to be charged.
+    pub missing_fees: Vec<Coin>,
+    /// Commission charged while bidding on more than was finally paid, refunded to the winner.
+    pub excess_fees: Vec<Coin>,
+}
+
+/// Splits a winner's escrow given what was `charged` while bidding. `paid` defaults to the whole
+/// escrow.
+pub(crate) fn settlement(escrow: &[Coin], charged: &[Coin], paid: Option<&Coin>) -> Result<Settlement, ContractError> {
+    let mut owed = vec![];
+    let mut refund = vec![];
+    let mut missing_fees = vec![];
+    let mut excess_fees = vec![];
+    for (deposit, paid) in escrow.iter().zip(paid_amounts(escrow, paid)) {
+        let already_charged = charged.iter()
+            .find(|fee| fee.denom == deposit.denom)
+            .map(|fee| fee.amount)
+            .unwrap_or_default();
+        let commission = paid.commission()?.amount;
+        let missing = commission.saturating_sub(already_charged);
+        let excess = already_charged.saturating_sub(commission);
+        add_coin(&mut missing_fees, &coin(missing.u128(), &deposit.denom));
+        add_coin(&mut excess_fees, &coin(excess.u128(), &deposit.denom));
+        add_coin(&mut owed, &paid.net_of_commission()?);
+
+        // The winner only pays commission on what they paid, so everything else comes back.
+        let remaining = deposit.amount - paid.amount;
+        add_coin(&mut refund, &coin(remaining.u128(), &deposit.denom));
+    }
+    Ok(Settlement {
+        owed: non_zero(&owed),
+        refund: non_zero(&refund),
+        missing_fees: non_zero(&missing_fees),
+        excess_fees: non_zero(&excess_fees),
+    })
+}
+
+/// Adds `amount` to the coin of the same denom, keeping the list sorted by denom.
+pub(crate) fn add_coin(coins: &mut Vec<Coin>, amount: &Coin) {
+    match coins.iter_mut().find(|coin| coin.denom == amount.denom) {
+        Some(coin) => coin.amount += amount.amount,
+        None => {
+            coins.push(amount.clone());
+            coins.sort_by(|a, b| a.denom.cmp(&b.denom));
+        }
+    }
+}
+
+pub(crate) fn sub_coin(coins: &mut [Coin], amount: &Coin) -> StdResult<()> {
+    match coins.iter_mut().find(|coin| coin.denom == amount.denom) {
+        Some(coin) => coin.amount = coin.amount.checked_sub(amount.amount)?,
+        None => {
+            Uint128::zero().checked_sub(amount.amount)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn non_zero(coins: &[Coin]) -> Vec<Coin> {
+    coins.iter().filter(|coin| !coin.amount.is_zero()).cloned().collect()
+}
+
+
+/// Commission in percent.
 const DEFAULT_COMMISSION: u64 = 10;
 
+/// The commission is rounded down, so that rounding dust stays in the escrow instead of going
+/// to the fees. A commission cannot exceed the amount it is taken from, so the errors are only
+/// defensive.
 pub trait Commission {
-    fn commission(&self) -> Decimal;
-    fn commission_as_coin(&self) -> Coin;
-    fn amount(&self) -> Decimal;
-    fn amount_as_coin(&self) -> Coin;
+    fn commission(&self) -> Result<Coin, ContractError>;
+    /// What is left after the commission.
+    fn net_of_commission(&self) -> Result<Coin, ContractError>;
+}
+
+impl Commission for Coin {
+    fn commission(&self) -> Result<Coin, ContractError> {
+        let commission = self.amount
+            .checked_multiply_ratio(DEFAULT_COMMISSION, 100u64)
+            .map_err(|_| ContractError::Overflow {})?;
+        Ok(coin(commission.u128(), &self.denom))
+    }
+
+    fn net_of_commission(&self) -> Result<Coin, ContractError> {
+        let net = self.amount
+            .checked_sub(self.commission()?.amount)
+            .map_err(|_| ContractError::Overflow {})?;
+        Ok(coin(net.u128(), &self.denom))
+    }
 }
 
-impl Commission for Bid {
-    fn commission(&self) -> Decimal {
-        Decimal::new(self.coin.amount) * Decimal::percent(DEFAULT_COMMISSION)
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{coins, SubMsg};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR};
+
+    use crate::msg::ExecuteMsg;
+    use crate::testing::InstantiateMsgBuilder;
+
+    use super::*;
+
+    fn bid_msg() -> ExecuteMsg {
+        ExecuteMsg::MakeBid { units: None, price: None, lots: None, expires: None, on_behalf_of: None }
+    }
+
+    fn gas_limits(messages: &[SubMsg]) -> Vec<Option<u64>> {
+        messages.iter().map(|msg| msg.gas_limit).collect()
+    }
+
+    #[test]
+    fn settlement_hooks_have_a_gas_limit() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsgBuilder::new("gold").settlement_hook("hook").build();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(20, DENOM));
+        execute(deps.as_mut(), mock_env(), mock_info("sender", &coins(20, DENOM)), bid_msg()).unwrap();
+
+        let resp = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::Close {}).unwrap();
+
+        assert_eq!(gas_limits(&resp.messages), vec![Some(SETTLEMENT_HOOK_GAS_LIMIT)]);
     }
 
-    fn commission_as_coin(&self) -> Coin {
-        coin(self.commission().atomics().u128(), DENOM)
+    #[test]
+    fn outbid_hooks_have_a_gas_limit() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsgBuilder::new("gold").build();
+        instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+        let register = ExecuteMsg::RegisterOutbidHook { contract: "hook".to_string() };
+        execute(deps.as_mut(), mock_env(), mock_info("sender1", &[]), register).unwrap();
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(10, DENOM));
+        execute(deps.as_mut(), mock_env(), mock_info("sender1", &coins(10, DENOM)), bid_msg()).unwrap();
+
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(30, DENOM));
+        let resp = execute(deps.as_mut(), mock_env(), mock_info("sender2", &coins(20, DENOM)), bid_msg()).unwrap();
+
+        assert_eq!(gas_limits(&resp.messages), vec![Some(OUTBID_HOOK_GAS_LIMIT)]);
     }
 
-    fn amount(&self) -> Decimal {
-        Decimal::new(self.coin.amount) - self.commission()
+    #[test]
+    fn commission_math() {
+        let max = Uint128::MAX;
+
+        assert_eq!(coin(0, DENOM).commission().unwrap(), coin(0, DENOM));
+        // Rounded down, so that the dust stays in the escrow.
+        assert_eq!(coin(9, DENOM).commission().unwrap(), coin(0, DENOM));
+        assert_eq!(coin(19, DENOM).commission().unwrap(), coin(1, DENOM));
+        assert_eq!(coin(19, DENOM).net_of_commission().unwrap(), coin(18, DENOM));
+
+        assert_eq!(coin(max.u128(), DENOM).commission().unwrap(), coin(max.u128() / 10, DENOM));
+        assert_eq!(coin(max.u128(), DENOM).net_of_commission().unwrap(), coin(max.u128() - max.u128() / 10, DENOM));
+        assert_eq!(coin(max.u128() - 1, DENOM).commission().unwrap(), coin((max.u128() - 1) / 10, DENOM));
+
+        let escrow = coins(max.u128(), DENOM);
+        let charged = coins(max.u128() / 10, DENOM);
+
+        assert_eq!(settlement(&escrow, &charged, None).unwrap(), Settlement {
+            owed: coins(max.u128() - max.u128() / 10, DENOM),
+            refund: vec![],
+            missing_fees: vec![],
+            excess_fees: vec![],
+        });
+
+        // Commission charged on the whole escrow is given back beyond what is due on the part paid.
+        let paid = coin(max.u128() / 2, DENOM);
+
+        assert_eq!(settlement(&escrow, &charged, Some(&paid)).unwrap(), Settlement {
+            owed: coins(max.u128() / 2 - max.u128() / 20, DENOM),
+            refund: coins(max.u128() - max.u128() / 2, DENOM),
+            missing_fees: vec![],
+            excess_fees: coins(max.u128() / 10 - max.u128() / 20, DENOM),
+        });
     }
 
-    fn amount_as_coin(&self) -> Coin {
-        coin( self.amount().atomics().u128(), DENOM)
+    #[test]
+    fn weighted_overflow() {
+        assert_eq!(weighted(Uint128::new(10), Decimal::percent(150)).unwrap(), Uint128::new(15));
+        assert_eq!(weighted(Uint128::MAX, Decimal::one()).unwrap(), Uint128::MAX);
+        assert_eq!(weighted(Uint128::MAX, Decimal::percent(200)).unwrap_err(), ContractError::Overflow {});
     }
 }
@@ -1,4 +1,5 @@
 use cosmwasm_std::{StdError, Uint128};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +7,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -20,4 +24,65 @@ pub enum ContractError {
 
     #[error("No retractable bid")]
     NoRectractableBid {},
+
+    #[error("Auction is closed")]
+    AuctionClosed {},
+
+    #[error("No fees to claim")]
+    NoFeesToClaim {},
+
+    #[error("Denom {denom} is not accepted")]
+    UnsupportedDenom { denom: String },
+
+    #[error("Accepted denoms must be unique with non-zero weights and start with a base denom of weight one")]
+    InvalidDenoms {},
+
+    #[error("Bidder is not allowed")]
+    BidderNotAllowed {},
+
+    #[error("Bid cap exceeded: cap {cap}; attempted {attempted}")]
+    BidCapExceeded { cap: Uint128, attempted: Uint128 },
+
+    #[error("Multi-unit auctions need a non-zero quantity and a single accepted denom")]
+    InvalidQuantity {},
+
+    #[error("Units and a unit price are required in multi-unit auctions and not accepted otherwise")]
+    InvalidUnitBid {},
+
+    #[error("Invalid lots: bundled auctions take up to 8 unique lots, and bids have to target known lots")]
+    InvalidLots {},
+
+    #[error("Unit bid does not match escrow: required {required}; deposited {deposited}")]
+    UnitBidMismatch { required: Uint128, deposited: Uint128 },
+
+    #[error("Proxy bids take single lot auctions, base denom funds only and an escrow equal to the max, and replace plain bids")]
+    InvalidProxyBid {},
+
+    #[error("Bid expiration is already in the past")]
+    InvalidExpiration {},
+
+    #[error("Bid is funded by another payer")]
+    PayerMismatch {},
+
+    #[error("Operator is not approved or the approval expired")]
+    OperatorNotApproved {},
+
+    #[error("Bid is below the starting price: amount {amount}; starting price {starting_price}")]
+    BelowStartingPrice { amount: Uint128, starting_price: Uint128 },
+
+    #[error("Royalty shares have to be positive and leave room for the commission")]
+    InvalidRoyalties {},
+
+    #[error("Bid cannot be decreased to zero; retract it instead")]
+    DecreaseToZero {},
+
+    #[error("Nothing to withdraw")]
+    NothingToWithdraw {},
+
+    /// Weighting bid values can overflow; the commission math only returns it defensively.
+    #[error("Overflow computing an amount")]
+    Overflow {},
+
+    #[error("Unknown reply id {id}")]
+    UnknownReply { id: u64 },
 }
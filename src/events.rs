@@ -0,0 +1,186 @@
+use cosmwasm_std::{Addr, Coin, Event};
+
+use crate::state::{Allocation, Approval};
+
+// The chain prefixes custom event types with `wasm-`, so `bid_placed` is indexed as
+// `wasm-bid_placed`. It also rejects empty attribute values, so attributes without a value
+// (no coins, no winner) are left out.
+pub const AUCTION_CREATED: &str = "auction_created";
+pub const BID_PLACED: &str = "bid_placed";
+pub const BID_RAISED: &str = "bid_raised";
+pub const BID_DECREASED: &str = "bid_decreased";
+pub const BID_RETRACTED: &str = "bid_retracted";
+pub const BID_EXPIRED: &str = "bid_expired";
+pub const AUCTION_CLOSED: &str = "auction_closed";
+pub const UNITS_ALLOCATED: &str = "units_allocated";
+pub const LOTS_SETTLED: &str = "lots_settled";
+pub const ROYALTY_PAID: &str = "royalty_paid";
+pub const VAULT_YIELD: &str = "vault_yield";
+pub const FEES_CLAIMED: &str = "fees_claimed";
+pub const WITHDRAWN: &str = "withdrawn";
+pub const ACCESS_LIST_UPDATED: &str = "access_list_updated";
+pub const OPERATOR_APPROVED: &str = "operator_approved";
+pub const OPERATOR_REVOKED: &str = "operator_revoked";
+pub const SETTLEMENT_HOOK_FAILED: &str = "settlement_hook_failed";
+pub const OUTBID_HOOK_REGISTERED: &str = "outbid_hook_registered";
+pub const OUTBID_HOOK_FAILED: &str = "outbid_hook_failed";
+
+/// Formats coins the way the bank module does, e.g. `10atom,5usdc`.
+pub fn coins_to_string(coins: &[Coin]) -> String {
+    coins.iter().map(Coin::to_string).collect::<Vec<_>>().join(",")
+}
+
+fn add_coins(event: Event, key: &str, coins: &[Coin]) -> Event {
+    if coins.is_empty() {
+        event
+    } else {
+        event.add_attribute(key, coins_to_string(coins))
+    }
+}
+
+pub fn auction_created(owner: &Addr, commodity: &str, deposit: &[Coin]) -> Event {
+    let mut event = Event::new(AUCTION_CREATED)
+        .add_attribute("owner", owner.as_str());
+    if !commodity.is_empty() {
+        event = event.add_attribute("commodity", commodity);
+    }
+    add_coins(event, "deposit", deposit)
+}
+
+/// `total` is the bidder's whole bid valued in the base denom, and `payer` who funded it.
+pub fn bid_placed(bidder: &Addr, payer: &Addr, amount: &[Coin], total: &Coin, commission: &[Coin]) -> Event {
+    let event = Event::new(BID_PLACED)
+        .add_attribute("bidder", bidder.as_str())
+        .add_attribute("payer", payer.as_str())
+        .add_attribute("amount", coins_to_string(amount))
+        .add_attribute("total", total.to_string());
+    add_coins(event, "commission", commission)
+}
+
+/// A proxy bid raised automatically to `total`.
+pub fn bid_raised(bidder: &Addr, total: &Coin) -> Event {
+    Event::new(BID_RAISED)
+        .add_attribute("bidder", bidder.as_str())
+        .add_attribute("total", total.to_string())
+}
+
+/// `amount` is what was withdrawn from escrow and `total` the bid left.
+pub fn bid_decreased(bidder: &Addr, amount: &[Coin], total: &Coin, refund: &[Coin]) -> Event {
+    let event = Event::new(BID_DECREASED)
+        .add_attribute("bidder", bidder.as_str())
+        .add_attribute("amount", coins_to_string(amount))
+        .add_attribute("total", total.to_string());
+    add_coins(event, "refund", refund)
+}
+
+pub fn bid_retracted(bidder: &Addr, receiver: &Addr, amount: &[Coin]) -> Event {
+    let event = Event::new(BID_RETRACTED)
+        .add_attribute("bidder", bidder.as_str())
+        .add_attribute("receiver", receiver.as_str());
+    add_coins(event, "amount", amount)
+}
+
+/// An expired bid refunded by pruning.
+pub fn bid_expired(bidder: &Addr, refund: &[Coin]) -> Event {
+    let event = Event::new(BID_EXPIRED)
+        .add_attribute("bidder", bidder.as_str());
+    add_coins(event, "refund", refund)
+}
+
+/// `winners` is left out when the auction closes without a bid.
+pub fn auction_closed(owner: &Addr, winners: &[Addr], bid: &Coin, payout: &[Coin]) -> Event {
+    let mut event = Event::new(AUCTION_CLOSED)
+        .add_attribute("owner", owner.as_str());
+    if !winners.is_empty() {
+        event = event.add_attribute("winner", winners.iter().map(Addr::as_str).collect::<Vec<_>>().join(","));
+    }
+    let event = event.add_attribute("bid", bid.to_string());
+    add_coins(event, "payout", payout)
+}
+
+pub fn units_allocated(allocation: &Allocation, refund: &[Coin]) -> Event {
+    let event = Event::new(UNITS_ALLOCATED)
+        .add_attribute("bidder", allocation.address.as_str())
+        .add_attribute("units", allocation.units.to_string())
+        .add_attribute("price", allocation.price.to_string());
+    add_coins(event, "refund", refund)
+}
+
+pub fn lots_settled(allocation: &Allocation, payout: &[Coin]) -> Event {
+    let lots = allocation.lots.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+    let event = Event::new(LOTS_SETTLED)
+        .add_attribute("winner", allocation.address.as_str())
+        .add_attribute("lots", lots)
+        .add_attribute("bid", allocation.price.to_string());
+    add_coins(event, "payout", payout)
+}
+
+pub fn royalty_paid(recipient: &Addr, amount: &[Coin]) -> Event {
+    Event::new(ROYALTY_PAID)
+        .add_attribute("recipient", recipient.as_str())
+        .add_attribute("amount", coins_to_string(amount))
+}
+
+/// `to_owner` is the part of the yield paid to the owner, the rest goes back to the bidders.
+pub fn vault_yield(amount: &[Coin], to_owner: &[Coin]) -> Event {
+    let event = add_coins(Event::new(VAULT_YIELD), "amount", amount);
+    add_coins(event, "to_owner", to_owner)
+}
+
+pub fn fees_claimed(recipient: &Addr, amount: &[Coin]) -> Event {
+    let event = Event::new(FEES_CLAIMED)
+        .add_attribute("recipient", recipient.as_str());
+    add_coins(event, "amount", amount)
+}
+
+pub fn withdrawn(address: &Addr, receiver: &Addr, amount: &[Coin]) -> Event {
+    let event = Event::new(WITHDRAWN)
+        .add_attribute("address", address.as_str())
+        .add_attribute("receiver", receiver.as_str());
+    add_coins(event, "amount", amount)
+}
+
+pub fn access_list_updated(added: &[Addr], removed: &[Addr]) -> Event {
+    let mut event = Event::new(ACCESS_LIST_UPDATED);
+    if !added.is_empty() {
+        event = event.add_attribute("added", added.iter().map(Addr::as_str).collect::<Vec<_>>().join(","));
+    }
+    if !removed.is_empty() {
+        event = event.add_attribute("removed", removed.iter().map(Addr::as_str).collect::<Vec<_>>().join(","));
+    }
+    event
+}
+
+pub fn operator_approved(granter: &Addr, operator: &Addr, approval: &Approval) -> Event {
+    let event = Event::new(OPERATOR_APPROVED)
+        .add_attribute("granter", granter.as_str())
+        .add_attribute("operator", operator.as_str())
+        .add_attribute("expires", approval.expires.to_string())
+        .add_attribute("max_amount", approval.max_amount.to_string());
+    add_coins(event, "reserve", &approval.reserve)
+}
+
+/// `refund` is what was left of the operator's reserve.
+pub fn operator_revoked(granter: &Addr, operator: &Addr, refund: &[Coin]) -> Event {
+    let event = Event::new(OPERATOR_REVOKED)
+        .add_attribute("granter", granter.as_str())
+        .add_attribute("operator", operator.as_str());
+    add_coins(event, "refund", refund)
+}
+
+pub fn settlement_hook_failed(winner: &Addr, error: &str) -> Event {
+    Event::new(SETTLEMENT_HOOK_FAILED)
+        .add_attribute("winner", winner.as_str())
+        .add_attribute("error", error)
+}
+
+pub fn outbid_hook_registered(bidder: &Addr, hook: &Addr) -> Event {
+    Event::new(OUTBID_HOOK_REGISTERED)
+        .add_attribute("bidder", bidder.as_str())
+        .add_attribute("hook", hook.as_str())
+}
+
+pub fn outbid_hook_failed(error: &str) -> Event {
+    Event::new(OUTBID_HOOK_FAILED)
+        .add_attribute("error", error)
+}
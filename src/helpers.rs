@@ -0,0 +1,107 @@
+use cosmwasm_std::{to_binary, Addr, Coin, CosmosMsg, QuerierWrapper, StdResult, WasmMsg};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::msg::{
+    AccessListResponse, AccountingResponse, ApprovalResponse, BidsResponse, ClaimableResponse, ConfigResponse,
+    EscrowResponse, ExecuteMsg, FailedSettlementsResponse, FeesResponse, QueryMsg, WinnersResponse,
+};
+
+/// Wraps the address of a bidding contract to build messages for it and query it from other
+/// contracts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BiddingContractClient(pub Addr);
+
+impl BiddingContractClient {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call<T: Into<ExecuteMsg>>(&self, msg: T, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = to_binary(&msg.into())?;
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg,
+            funds,
+        }
+            .into())
+    }
+
+    /// A single lot bid of `funds`.
+    pub fn make_bid(&self, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::MakeBid { units: None, price: None, lots: None, expires: None, on_behalf_of: None };
+        self.call(msg, funds)
+    }
+
+    pub fn close(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Close {}, vec![])
+    }
+
+    pub fn retract(&self, receiver: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Retract { receiver, on_behalf_of: None }, vec![])
+    }
+
+    pub fn claim_fees(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ClaimFees {}, vec![])
+    }
+
+    pub fn withdraw(&self, receiver: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Withdraw { receiver }, vec![])
+    }
+
+    /// Has the sender notified at `contract` when they are outbid.
+    pub fn register_outbid_hook(&self, contract: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RegisterOutbidHook { contract: contract.into() }, vec![])
+    }
+
+    pub fn query_config(&self, querier: &QuerierWrapper) -> StdResult<ConfigResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Config {})
+    }
+
+    pub fn query_bids(&self, querier: &QuerierWrapper) -> StdResult<BidsResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Bids {})
+    }
+
+    pub fn query_fees(&self, querier: &QuerierWrapper) -> StdResult<FeesResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Fees {})
+    }
+
+    pub fn query_escrow(&self, querier: &QuerierWrapper, address: impl Into<String>) -> StdResult<EscrowResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Escrow { address: address.into() })
+    }
+
+    pub fn query_access_list(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<AccessListResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::AccessList { start_after, limit })
+    }
+
+    pub fn query_winners(&self, querier: &QuerierWrapper) -> StdResult<WinnersResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Winners {})
+    }
+
+    pub fn query_approval(
+        &self,
+        querier: &QuerierWrapper,
+        granter: impl Into<String>,
+        operator: impl Into<String>,
+    ) -> StdResult<ApprovalResponse> {
+        let msg = QueryMsg::Approval { granter: granter.into(), operator: operator.into() };
+        querier.query_wasm_smart(self.addr(), &msg)
+    }
+
+    pub fn query_accounting(&self, querier: &QuerierWrapper) -> StdResult<AccountingResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Accounting {})
+    }
+
+    pub fn query_failed_settlements(&self, querier: &QuerierWrapper) -> StdResult<FailedSettlementsResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::FailedSettlements {})
+    }
+
+    pub fn query_claimable(&self, querier: &QuerierWrapper, address: impl Into<String>) -> StdResult<ClaimableResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::Claimable { address: address.into() })
+    }
+}
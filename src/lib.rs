@@ -1,8 +1,16 @@
+mod bundles;
 pub mod contract;
 mod error;
+pub mod events;
+pub mod helpers;
 pub mod msg;
 pub mod state;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+mod vault;
 
 pub use crate::error::ContractError;
 
+#[cfg(test)]
 mod multitest;
@@ -1,23 +1,184 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Coin};
+use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+use cw_utils::Expiration;
 
-use crate::state::Config;
+use crate::state::{AcceptedDenom, AccessMode, Allocation, Approval, Config, Lot, YieldRecipient};
 
 #[cw_serde]
+#[derive(Default)]
 pub struct InstantiateMsg {
     pub commodity: String,
     pub owner: Option<String>,
+    /// Defaults to the owner.
+    pub fee_recipient: Option<String>,
+    /// Whether bidders that retract are charged the commission. Defaults to `true`.
+    pub charge_losing_bidders: Option<bool>,
+    /// Defaults to `atom` only. The first denom is the base and needs a weight of one.
+    #[serde(default)]
+    pub denoms: Vec<AcceptedDenom>,
+    /// Defaults to open bidding.
+    pub access_mode: Option<AccessMode>,
+    pub member_group: Option<MemberGroupMsg>,
+    pub max_bid_per_address: Option<Uint128>,
+    pub max_total_escrow: Option<Uint128>,
+    /// Sells this many identical units at a uniform price instead of a single lot. Needs a single
+    /// accepted denom.
+    pub quantity: Option<u64>,
+    /// Sells these lots individually or bundled instead of a single lot. At most eight lots, and
+    /// not together with `quantity`.
+    #[serde(default)]
+    pub lots: Vec<Lot>,
+    /// Bids have to exceed the leading bid by more than this. Defaults to zero.
+    pub min_increment: Option<Uint128>,
+    /// Lowest opening bid, and lowest unit price in a multi-unit auction. Defaults to zero.
+    pub starting_price: Option<Uint128>,
+    /// Contract to notify with `AuctionSettled` for every winner on close. Each notification may
+    /// use up to `SETTLEMENT_HOOK_GAS_LIMIT` gas.
+    pub settlement_hook: Option<String>,
+    /// Shares of the winning amount paid to each address on close. Together with the commission
+    /// they cannot exceed the whole amount.
+    #[serde(default)]
+    pub royalties: Vec<RoyaltyMsg>,
+    /// Deposits escrowed bids in a vault while the auction runs.
+    pub vault: Option<VaultMsg>,
+}
+
+#[cw_serde]
+pub struct VaultMsg {
+    /// Address of a contract implementing `VaultExecuteMsg` and `VaultQueryMsg`.
+    pub contract: String,
+    pub yield_recipient: YieldRecipient,
+}
+
+#[cw_serde]
+pub struct RoyaltyMsg {
+    pub address: String,
+    pub share: Decimal,
+}
+
+#[cw_serde]
+pub struct MemberGroupMsg {
+    /// Address of a cw4 group contract.
+    pub contract: String,
+    pub min_weight: Option<u64>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    MakeBid {},
+    /// In a multi-unit auction `units` and the per-unit `price` are required, and the bidder's
+    /// whole escrow has to equal `units * price`. With bundled lots `lots` lists the lot ids the
+    /// bid is on; top-ups have to target the same lots. `expires` replaces any earlier expiration
+    /// of the bidder's bid; expired bids stop counting and can be pruned. With `on_behalf_of` the
+    /// sender funds a bid for that address and gets its refunds.
+    MakeBid {
+        units: Option<u64>,
+        price: Option<Uint128>,
+        lots: Option<Vec<u32>>,
+        expires: Option<Expiration>,
+        on_behalf_of: Option<String>,
+    },
+    /// Bids automatically on the sender's behalf up to `max`, which has to be escrowed in full
+    /// in the base denom. Only the effective bid is shown, never the max. Single lot auctions only.
+    SetMaxBid {
+        max: Uint128,
+    },
     Close {},
+    /// Retracts the sender's bid, or the one placed for `on_behalf_of`. The payer can retract to
+    /// any `receiver`, which defaults to the payer; the beneficiary only back to the payer.
     Retract {
         receiver: Option<String>,
+        on_behalf_of: Option<String>,
+    },
+    /// Withdraws `amount` from the sender's escrow. The leader has to stay ahead of the runner-up,
+    /// and withdrawing everything takes a `Retract`. Single lot auctions only.
+    DecreaseBid {
+        amount: Vec<Coin>,
+    },
+    ClaimFees {},
+    /// Lets `operator` raise and retract the sender's bid. Funds sent along make up the reserve
+    /// the operator raises the bid with. `expires` defaults to never.
+    ApproveOperator {
+        operator: String,
+        expires: Option<Expiration>,
+        max_amount: Uint128,
+    },
+    /// Revokes the approval and refunds what is left of its reserve.
+    RevokeOperator {
+        operator: String,
+    },
+    /// Raises the granter's bid by `amount` taken from the sender's reserve as operator.
+    RaiseBid {
+        granter: String,
+        amount: Vec<Coin>,
+    },
+    /// Refunds up to `limit` expired bids, 10 by default and 30 at most.
+    PruneExpired {
+        limit: Option<u32>,
+    },
+    UpdateAccessList {
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    /// Sends `Outbid` to `contract` whenever the sender stops winning because of another bid,
+    /// replacing any hook registered before. Hooks may use up to `OUTBID_HOOK_GAS_LIMIT` gas, and
+    /// failing ones are ignored.
+    RegisterOutbidHook {
+        contract: String,
+    },
+    /// Sends everything credited to the sender by closes, retracts, refunds and fee claims to
+    /// `receiver`, which defaults to the sender.
+    Withdraw {
+        receiver: Option<String>,
+    },
+}
+
+/// Sent to the settlement hook on close. A failing hook is recorded and does not fail the close.
+#[cw_serde]
+pub enum SettlementHookMsg {
+    /// `price` is what the winner paid, valued in the base denom.
+    AuctionSettled {
+        winner: Addr,
+        price: Coin,
+        commodity: String,
+    },
+}
+
+/// Sent to a bidder's outbid hook.
+#[cw_serde]
+pub enum OutbidHookMsg {
+    Outbid {
+        bidder: Addr,
+        commodity: String,
+    },
+}
+
+/// What a vault has to accept. `Deposit` credits the funds sent along to the sender and
+/// `Withdraw` sends `amount` of the sender's balance back to them.
+#[cw_serde]
+pub enum VaultExecuteMsg {
+    Deposit {},
+    Withdraw {
+        amount: Vec<Coin>,
     },
 }
 
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum VaultQueryMsg {
+    /// The address's deposits including the yield accrued on them.
+    #[returns(VaultBalanceResponse)]
+    Balance {
+        address: String,
+    },
+}
+
+#[cw_serde]
+pub struct VaultBalanceResponse {
+    pub amount: Vec<Coin>,
+}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
@@ -25,6 +186,32 @@ pub enum QueryMsg {
     Config {},
     #[returns(BidsResponse)]
     Bids {},
+    #[returns(FeesResponse)]
+    Fees {},
+    #[returns(EscrowResponse)]
+    Escrow {
+        address: String,
+    },
+    #[returns(AccessListResponse)]
+    AccessList {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(WinnersResponse)]
+    Winners {},
+    #[returns(ApprovalResponse)]
+    Approval {
+        granter: String,
+        operator: String,
+    },
+    #[returns(AccountingResponse)]
+    Accounting {},
+    #[returns(FailedSettlementsResponse)]
+    FailedSettlements {},
+    #[returns(ClaimableResponse)]
+    Claimable {
+        address: String,
+    },
 }
 
 #[cw_serde]
@@ -42,3 +229,69 @@ pub struct Bid {
 pub struct BidsResponse {
     pub bids: Vec<Bid>,
 }
+
+#[cw_serde]
+pub struct FeesResponse {
+    pub recipient: Addr,
+    pub fees: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct EscrowResponse {
+    pub address: Addr,
+    pub coins: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct AccessListResponse {
+    pub mode: AccessMode,
+    pub addresses: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct WinnersResponse {
+    pub winners: Vec<Allocation>,
+}
+
+#[cw_serde]
+pub struct ApprovalResponse {
+    pub approval: Option<Approval>,
+}
+
+/// `payout_owed`, `refundable`, `fees` and `reserves` are what the contract would pay out if the
+/// auction closed now and everyone withdrew; `balance` and `vault` together have to cover them.
+#[cw_serde]
+pub struct AccountingResponse {
+    /// All escrowed bids, including the commission already charged on them.
+    pub total_escrow: Vec<Coin>,
+    /// Unclaimed fees, including the commission the current winners still pay on close.
+    pub fees: Vec<Coin>,
+    /// What the bidders get back, net of commission.
+    pub refundable: Vec<Coin>,
+    /// What the owner and the royalty recipients get on close, the owner's deposit included.
+    pub payout_owed: Vec<Coin>,
+    /// Operator reserves not spent on bids yet.
+    pub reserves: Vec<Coin>,
+    /// Payouts credited and not withdrawn yet.
+    pub claimable: Vec<Coin>,
+    pub balance: Vec<Coin>,
+    /// Principal deposited in the vault, without the yield.
+    pub vault: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct FailedSettlement {
+    pub winner: Addr,
+    pub error: String,
+}
+
+#[cw_serde]
+pub struct FailedSettlementsResponse {
+    pub failures: Vec<FailedSettlement>,
+}
+
+#[cw_serde]
+pub struct ClaimableResponse {
+    pub address: Addr,
+    pub amount: Vec<Coin>,
+}
@@ -1,3 +1,5 @@
 pub mod contract;
 #[cfg(test)]
+mod proptests;
+#[cfg(test)]
 mod tests;
\ No newline at end of file
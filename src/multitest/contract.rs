@@ -1,116 +1,197 @@
-use cosmwasm_std::{Addr, Coin, coin, coins, StdResult};
-use cw_multi_test::{App, BasicApp, ContractWrapper, Executor};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, BankMsg, Binary, Coin, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult, to_binary,
+};
+use cw4::Member;
+use cw_multi_test::{App, AppResponse, ContractWrapper, Executor};
+use cw_storage_plus::{Item, Map};
+use serde::{Deserialize, Serialize};
 
-use crate::contract::{execute, instantiate, query};
-use crate::error::ContractError;
-use crate::msg::{BidsResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::contract::{add_coin, non_zero, sub_coin};
+use crate::msg::{OutbidHookMsg, SettlementHookMsg, VaultBalanceResponse, VaultQueryMsg};
 
-pub struct BiddingContract(Addr);
+pub use crate::testing::BiddingContract;
 
-impl BiddingContract {
+/// A cw4 group the bidding contract can be gated on.
+#[derive(Debug)]
+pub struct Cw4GroupContract(Addr);
+
+impl Cw4GroupContract {
     pub fn addr(&self) -> &Addr {
         &self.0
     }
 
-    pub fn app_with_funds(sender: impl Into<Option<Addr>>, amount: impl Into<Option<u128>>) -> BasicApp {
-        App::new(|router, _api, storage| {
-            router
-                .bank
-                .init_balance(
-                    storage,
-                    &sender.into().unwrap_or_else(|| Addr::unchecked("owner")),
-                    coins(amount.into().unwrap_or_else(|| 0), "atom"))
-                .unwrap();
-        })
-    }
-
     pub fn store_code(app: &mut App) -> u64 {
-        let contract = ContractWrapper::new(execute, instantiate, query);
+        let contract = ContractWrapper::new(
+            cw4_group::contract::execute,
+            cw4_group::contract::instantiate,
+            cw4_group::contract::query,
+        );
         app.store_code(Box::new(contract))
     }
 
     #[track_caller]
-    pub fn instantiate<'a>(
+    pub fn instantiate(
         app: &mut App,
         code_id: u64,
-        sender: impl Into<Option<&'a Addr>>,
-        owner: impl Into<Option<&'a Addr>>,
-        admin: impl Into<Option<&'a Addr>>,
-        commodity: impl Into<Option<&'a str>>,
-        bid: impl Into<Option<u128>>,
+        sender: &Addr,
+        members: &[(&Addr, u64)],
     ) -> StdResult<Self> {
-        let sender = sender.into().cloned().unwrap_or_else(|| Addr::unchecked("sender"));
-        let admin = admin.into().map(Addr::to_string);
-        let owner = owner.into().map(Addr::to_string);
-        let commodity = commodity.into().unwrap_or_else(|| "gold").to_string();
-        let bid = bid.into().map(|b| vec![coin(b, "atom")]).unwrap_or_else(|| vec![]);
+        let members = members.iter()
+            .map(|(addr, weight)| Member { addr: addr.to_string(), weight: *weight })
+            .collect();
 
         app.instantiate_contract(
             code_id,
-            sender,
-            &InstantiateMsg {
-                commodity,
-                owner,
-            },
-            bid.as_slice(),
-            "Bidding contract",
-            admin,
+            sender.clone(),
+            &cw4_group::msg::InstantiateMsg { admin: None, members },
+            &[],
+            "Group contract",
+            None,
         )
-            .map(BiddingContract)
+            .map(Cw4GroupContract)
             .map_err(|err| err.downcast().unwrap())
     }
+}
+
+/// Any message the bidding contract sends to hooks.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum HookMsg {
+    Settlement(SettlementHookMsg),
+    Outbid(OutbidHookMsg),
+}
+
+/// How a `HookContract` handles the hook messages it receives.
+#[cw_serde]
+pub enum HookBehavior {
+    Record,
+    Fail,
+    /// Fails like a hook that used up its gas limit; multitest does not meter gas.
+    RunOutOfGas,
+}
+
+/// Records the hook messages it receives, or fails on all of them.
+#[derive(Debug)]
+pub struct HookContract(Addr);
+
+const BEHAVIOR: Item<HookBehavior> = Item::new("behavior");
+const RECEIVED: Item<Vec<HookMsg>> = Item::new("received");
+
+impl HookContract {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn store_code(app: &mut App) -> u64 {
+        let contract = ContractWrapper::new(
+            |deps: DepsMut, _env: Env, _info: MessageInfo, msg: HookMsg| -> StdResult<Response> {
+                match BEHAVIOR.load(deps.storage)? {
+                    HookBehavior::Record => {}
+                    HookBehavior::Fail => return Err(StdError::generic_err("hook failed")),
+                    HookBehavior::RunOutOfGas => return Err(StdError::generic_err("out of gas")),
+                }
+                let mut received = RECEIVED.load(deps.storage)?;
+                received.push(msg);
+                RECEIVED.save(deps.storage, &received)?;
+                Ok(Response::new())
+            },
+            |deps: DepsMut, _env: Env, _info: MessageInfo, behavior: HookBehavior| -> StdResult<Response> {
+                BEHAVIOR.save(deps.storage, &behavior)?;
+                RECEIVED.save(deps.storage, &vec![])?;
+                Ok(Response::new())
+            },
+            |deps: Deps, _env: Env, _msg: Empty| -> StdResult<Binary> {
+                to_binary(&RECEIVED.load(deps.storage)?)
+            },
+        );
+        app.store_code(Box::new(contract))
+    }
 
     #[track_caller]
-    pub fn make_bid(
-        &self,
-        app: &mut App,
-        sender: &Addr,
-        funds: &[Coin],
-    ) -> Result<(), ContractError> {
-        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::MakeBid {}, funds)
+    pub fn instantiate(app: &mut App, code_id: u64, sender: &Addr, behavior: HookBehavior) -> StdResult<Self> {
+        app.instantiate_contract(code_id, sender.clone(), &behavior, &[], "Hook contract", None)
+            .map(HookContract)
             .map_err(|err| err.downcast().unwrap())
-            .map(|_| ())
     }
 
     #[track_caller]
-    pub fn close(
-        &self,
-        app: &mut App,
-        sender: &Addr
-    ) -> Result<(), ContractError> {
-        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::Close {}, &[])
-            .map_err(|err| err.downcast().unwrap())
-            .map(|_| ())
+    pub fn query_received(&self, app: &App) -> StdResult<Vec<HookMsg>> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &Empty {})
+    }
+}
+
+/// `Deposit` and `Withdraw` as vaults have to accept them, and `Accrue` to credit the funds sent
+/// along to `address` as yield.
+#[cw_serde]
+pub enum VaultMsg {
+    Deposit {},
+    Withdraw { amount: Vec<Coin> },
+    Accrue { address: String },
+}
+
+/// A vault that pays whatever yield is accrued to it.
+#[derive(Debug)]
+pub struct VaultContract(Addr);
+
+const DEPOSITS: Map<Addr, Vec<Coin>> = Map::new("deposits");
+
+impl VaultContract {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn store_code(app: &mut App) -> u64 {
+        let contract = ContractWrapper::new(
+            |deps: DepsMut, _env: Env, info: MessageInfo, msg: VaultMsg| -> StdResult<Response> {
+                let address = match &msg {
+                    VaultMsg::Accrue { address } => deps.api.addr_validate(address)?,
+                    _ => info.sender.clone(),
+                };
+                let mut resp = Response::new();
+                let mut deposits = DEPOSITS.may_load(deps.storage, address.clone())?.unwrap_or_default();
+                match msg {
+                    VaultMsg::Deposit {} | VaultMsg::Accrue { .. } => {
+                        info.funds.iter().for_each(|fund| add_coin(&mut deposits, fund));
+                    }
+                    VaultMsg::Withdraw { amount } => {
+                        amount.iter().try_for_each(|withdrawn| sub_coin(&mut deposits, withdrawn))?;
+                        resp = resp.add_message(BankMsg::Send { to_address: address.to_string(), amount });
+                    }
+                }
+                DEPOSITS.save(deps.storage, address, &non_zero(&deposits))?;
+                Ok(resp)
+            },
+            |_deps: DepsMut, _env: Env, _info: MessageInfo, _msg: Empty| -> StdResult<Response> {
+                Ok(Response::new())
+            },
+            |deps: Deps, _env: Env, msg: VaultQueryMsg| -> StdResult<Binary> {
+                let VaultQueryMsg::Balance { address } = msg;
+                let amount = DEPOSITS.may_load(deps.storage, deps.api.addr_validate(&address)?)?.unwrap_or_default();
+                to_binary(&VaultBalanceResponse { amount })
+            },
+        );
+        app.store_code(Box::new(contract))
     }
 
     #[track_caller]
-    pub fn retract<'a>(
-        &self,
-        app: &mut App,
-        sender: &Addr,
-        receiver: impl Into<Option<&'a Addr>>,
-    ) -> Result<(), ContractError> {
-        let receiver = receiver.into().map(Addr::to_string);
-        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::Retract {receiver}, &[])
+    pub fn instantiate(app: &mut App, code_id: u64, sender: &Addr) -> StdResult<Self> {
+        app.instantiate_contract(code_id, sender.clone(), &Empty {}, &[], "Vault contract", None)
+            .map(VaultContract)
             .map_err(|err| err.downcast().unwrap())
-            .map(|_| ())
     }
 
     #[track_caller]
-    pub fn query_config(&self, app: &App) -> StdResult<ConfigResponse> {
-        app.wrap()
-            .query_wasm_smart(self.0.clone(), &QueryMsg::Config {})
+    pub fn accrue(&self, app: &mut App, sender: &Addr, address: &Addr, funds: &[Coin]) -> StdResult<AppResponse> {
+        let msg = VaultMsg::Accrue { address: address.to_string() };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, funds)
+            .map_err(|err| err.downcast().unwrap())
     }
 
     #[track_caller]
-    pub fn query_bids(&self, app: &App) -> StdResult<BidsResponse> {
+    pub fn query_balance(&self, app: &App, address: &Addr) -> StdResult<VaultBalanceResponse> {
         app.wrap()
-            .query_wasm_smart(self.0.clone(), &QueryMsg::Bids {})
-    }
-}
-
-impl From<BiddingContract> for Addr {
-    fn from(contract: BiddingContract) -> Self {
-        contract.0
+            .query_wasm_smart(self.0.clone(), &VaultQueryMsg::Balance { address: address.to_string() })
     }
 }
@@ -0,0 +1,177 @@
+use cosmwasm_std::{Addr, coins, Uint128};
+use cw_multi_test::App;
+use proptest::prelude::*;
+
+use crate::msg::AccountingResponse;
+
+use super::contract::BiddingContract;
+
+const ATOM: &str = "atom";
+const BIDDERS: usize = 8;
+const FUNDS: u128 = 10_000;
+
+#[derive(Clone, Debug)]
+enum Action {
+    Bid { bidder: usize, amount: u128 },
+    Retract { bidder: usize },
+    Close,
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        4 => (0..BIDDERS, 1..100u128).prop_map(|(bidder, amount)| Action::Bid { bidder, amount }),
+        2 => (0..BIDDERS).prop_map(|bidder| Action::Retract { bidder }),
+        1 => Just(Action::Close),
+    ]
+}
+
+/// Sequences of actions; proptest shrinks failing ones down to a minimal reproduction.
+fn actions() -> impl Strategy<Value = Vec<Action>> {
+    prop::collection::vec(action(), 1..40)
+}
+
+/// An auction with funded bidders that actions are replayed against.
+struct Scenario {
+    app: App,
+    contract: BiddingContract,
+    owner: Addr,
+    bidders: Vec<Addr>,
+}
+
+impl Scenario {
+    fn new() -> Self {
+        let owner = Addr::unchecked("owner");
+        let bidders: Vec<_> = (0..BIDDERS).map(|i| Addr::unchecked(format!("bidder{}", i))).collect();
+
+        let mut app = App::new(|router, _api, storage| {
+            for bidder in &bidders {
+                router
+                    .bank
+                    .init_balance(storage, bidder, coins(FUNDS, ATOM))
+                    .unwrap();
+            }
+        });
+
+        let contract_id = BiddingContract::store_code(&mut app);
+
+        let contract = BiddingContract::instantiate(
+            &mut app,
+            contract_id,
+            &owner,
+            None,
+            None,
+            "gold",
+            None,
+        ).unwrap();
+
+        Scenario { app, contract, owner, bidders }
+    }
+
+    /// Rejected actions are fine, they just leave the auction as it was.
+    fn apply(&mut self, action: &Action) {
+        let _ = match *action {
+            Action::Bid { bidder, amount } => {
+                self.contract.make_bid(&mut self.app, &self.bidders[bidder], &coins(amount, ATOM))
+            }
+            Action::Retract { bidder } => self.contract.retract(&mut self.app, &self.bidders[bidder], None),
+            Action::Close => self.contract.close(&mut self.app, &self.owner),
+        };
+    }
+
+    /// Closes the auction, lets every bidder retract, claims the fees and withdraws everything credited.
+    fn settle(&mut self) {
+        let _ = self.contract.close(&mut self.app, &self.owner);
+        for bidder in &self.bidders {
+            let _ = self.contract.retract(&mut self.app, bidder, None);
+        }
+        let _ = self.contract.claim_fees(&mut self.app, &self.owner);
+        for address in self.bidders.iter().chain([&self.owner]) {
+            let _ = self.contract.withdraw(&mut self.app, address, None);
+        }
+    }
+
+    fn balance(&self, address: &Addr) -> Uint128 {
+        self.app.wrap().query_balance(address, ATOM).unwrap().amount
+    }
+
+    fn total_funds(&self) -> Uint128 {
+        self.bidders.iter()
+            .chain([&self.owner, self.contract.addr()])
+            .map(|address| self.balance(address))
+            .sum()
+    }
+
+    fn leading_bid(&self) -> Uint128 {
+        let bids = self.contract.query_bids(&self.app).unwrap().bids;
+        bids.iter().map(|bid| bid.coin.amount).max().unwrap_or_default()
+    }
+}
+
+fn owed(accounting: &AccountingResponse) -> Uint128 {
+    [&accounting.payout_owed, &accounting.refundable, &accounting.fees, &accounting.reserves, &accounting.claimable]
+        .iter()
+        .flat_map(|coins| coins.iter())
+        .map(|coin| coin.amount)
+        .sum()
+}
+
+proptest! {
+    #[test]
+    fn accounting_matches_balance(actions in actions()) {
+        let mut scenario = Scenario::new();
+
+        for action in &actions {
+            scenario.apply(action);
+
+            let accounting = scenario.contract.query_accounting(&scenario.app).unwrap();
+            let balance: Uint128 = accounting.balance.iter().map(|coin| coin.amount).sum();
+            prop_assert_eq!(owed(&accounting), balance);
+        }
+
+        // Settling everything empties the contract.
+        scenario.settle();
+        prop_assert_eq!(scenario.balance(scenario.contract.addr()), Uint128::zero());
+    }
+
+    #[test]
+    fn funds_are_conserved(actions in actions()) {
+        let mut scenario = Scenario::new();
+        let total = Uint128::new(FUNDS * BIDDERS as u128);
+
+        for action in &actions {
+            scenario.apply(action);
+            prop_assert_eq!(scenario.total_funds(), total);
+        }
+
+        scenario.settle();
+        prop_assert_eq!(scenario.total_funds(), total);
+    }
+
+    #[test]
+    fn leading_bid_never_drops(actions in actions()) {
+        let mut scenario = Scenario::new();
+        let mut leading = Uint128::zero();
+
+        for action in actions.iter().take_while(|action| !matches!(action, Action::Close)) {
+            scenario.apply(action);
+
+            let current = scenario.leading_bid();
+            prop_assert!(current >= leading, "leading bid dropped from {} to {}", leading, current);
+            leading = current;
+        }
+    }
+
+    #[test]
+    fn no_one_withdraws_more_than_deposited(actions in actions()) {
+        let mut scenario = Scenario::new();
+
+        for action in &actions {
+            scenario.apply(action);
+        }
+        scenario.settle();
+
+        for bidder in &scenario.bidders {
+            prop_assert!(scenario.balance(bidder) <= Uint128::new(FUNDS), "{} ended up with more than they had", bidder);
+        }
+    }
+}
@@ -1,13 +1,21 @@
-use cosmwasm_std::{Addr, coin, coins, Uint128};
-use cw_multi_test::App;
+use cosmwasm_std::{Addr, coin, coins, Decimal, Event, Uint128};
+use cw_multi_test::{App, Executor};
+use cw_utils::{Expiration, PaymentError};
 
 use crate::error::ContractError;
-use crate::msg::{Bid, BidsResponse, ConfigResponse};
-use crate::state::Config;
+use crate::helpers::BiddingContractClient;
+use crate::msg::{
+    AccessListResponse, Bid, BidsResponse, ConfigResponse, EscrowResponse, ExecuteMsg, FailedSettlementsResponse,
+    FeesResponse, InstantiateMsg, MemberGroupMsg, OutbidHookMsg, SettlementHookMsg, WinnersResponse,
+};
+use crate::state::{AcceptedDenom, AccessMode, Allocation, Config, Lot, YieldRecipient};
 
-use super::contract::BiddingContract;
+use crate::testing::{assert_balance, assert_bids, InstantiateMsgBuilder};
+
+use super::contract::{BiddingContract, Cw4GroupContract, HookBehavior, HookContract, HookMsg, VaultContract};
 
 const ATOM: &str = "atom";
+const USDC: &str = "usdc";
 
 #[test]
 fn instantiate() {
@@ -30,11 +38,28 @@ fn instantiate() {
 
     let resp: ConfigResponse = contract.query_config(&app).unwrap();
 
-    assert_eq!(resp, ConfigResponse { config: Config { owner: owner.clone(), commodity } });
+    assert_eq!(resp, ConfigResponse { config: Config {
+        owner: owner.clone(),
+        commodity,
+        fee_recipient: owner.clone(),
+        charge_losing_bidders: true,
+        denoms: vec![AcceptedDenom { denom: ATOM.to_string(), weight: Decimal::one() }],
+        access_mode: AccessMode::Open,
+        member_group: None,
+        max_bid_per_address: None,
+        max_total_escrow: None,
+        quantity: None,
+        lots: vec![],
+        min_increment: Uint128::zero(),
+        starting_price: Uint128::zero(),
+        settlement_hook: None,
+        royalties: vec![],
+        vault: None,
+    } });
 
     let resp: BidsResponse = contract.query_bids(&app).unwrap();
 
-    assert_eq!(resp, BidsResponse { bids: vec![Bid { address: owner, coin: coin(0, ATOM) }] });
+    assert_eq!(resp, BidsResponse { bids: vec![] });
 }
 
 #[test]
@@ -65,13 +90,34 @@ fn instantiate_with_bid() {
 
     let resp: ConfigResponse = contract.query_config(&app).unwrap();
 
-    assert_eq!(resp, ConfigResponse { config: Config { owner: owner.clone(), commodity } });
+    assert_eq!(resp, ConfigResponse { config: Config {
+        owner: owner.clone(),
+        commodity,
+        fee_recipient: owner.clone(),
+        charge_losing_bidders: true,
+        denoms: vec![AcceptedDenom { denom: ATOM.to_string(), weight: Decimal::one() }],
+        access_mode: AccessMode::Open,
+        member_group: None,
+        max_bid_per_address: None,
+        max_total_escrow: None,
+        quantity: None,
+        lots: vec![],
+        min_increment: Uint128::zero(),
+        starting_price: Uint128::zero(),
+        settlement_hook: None,
+        royalties: vec![],
+        vault: None,
+    } });
     assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), vec![]);
     assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(10, ATOM));
 
     let resp: BidsResponse = contract.query_bids(&app).unwrap();
 
-    assert_eq!(resp, BidsResponse { bids: vec![Bid { address: owner, coin: coin(10, ATOM) }] });
+    assert_eq!(resp, BidsResponse { bids: vec![] });
+
+    let resp: EscrowResponse = contract.query_escrow(&app, &owner).unwrap();
+
+    assert_eq!(resp, EscrowResponse { address: owner, coins: coins(10, ATOM) });
 }
 
 #[test]
@@ -166,14 +212,13 @@ fn bid_success() {
 
     assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), coins(10, ATOM));
     assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(20, ATOM));
-    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(9, ATOM));
-    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(1, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(10, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), vec![]);
 
     let resp: BidsResponse = contract.query_bids(&app).unwrap();
 
     assert_eq!(resp, BidsResponse { bids: vec![
         Bid { address: sender1.clone(), coin: coin(10, ATOM) },
-        Bid { address: owner.clone(), coin: coin(0, ATOM) },
     ] });
 
     contract
@@ -182,15 +227,14 @@ fn bid_success() {
 
     assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), coins(10, ATOM));
     assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(8, ATOM));
-    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(20, ATOM));
-    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(2, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(22, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), vec![]);
 
     let resp: BidsResponse = contract.query_bids(&app).unwrap();
 
     assert_eq!(resp, BidsResponse { bids: vec![
         Bid { address: sender2.clone(), coin: coin(12, ATOM) },
         Bid { address: sender1.clone(), coin: coin(10, ATOM) },
-        Bid { address: owner.clone(), coin: coin(0, ATOM) },
     ] });
 
     contract
@@ -199,15 +243,14 @@ fn bid_success() {
 
     assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), vec![]);
     assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(8, ATOM));
-    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(29, ATOM));
-    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(3, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(32, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), vec![]);
 
     let resp: BidsResponse = contract.query_bids(&app).unwrap();
 
     assert_eq!(resp, BidsResponse { bids: vec![
         Bid { address: sender1.clone(), coin: coin(20, ATOM) },
         Bid { address: sender2.clone(), coin: coin(12, ATOM) },
-        Bid { address: owner.clone(), coin: coin(0, ATOM) },
     ] });
 
     let err = contract
@@ -226,10 +269,26 @@ fn bid_success() {
         .close(&mut app, &owner)
         .unwrap();
 
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
     assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), vec![]);
     assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(8, ATOM));
-    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(11, ATOM));
-    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(21, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(14, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(18, ATOM));
+
+    let err = contract
+        .close(&mut app, &owner)
+        .unwrap_err();
+
+    assert_eq!(err,  ContractError::AuctionClosed { });
+
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(8, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err,  ContractError::AuctionClosed { });
 
     let err = contract
         .retract(&mut app, &sender1, None)
@@ -247,10 +306,43 @@ fn bid_success() {
         .retract(&mut app, &sender2, None)
         .unwrap();
 
+    contract
+        .withdraw(&mut app, &sender2, None)
+        .unwrap();
+
     assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), vec![]);
     assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(19, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(3, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(18, ATOM));
+
+    let err = contract
+        .retract(&mut app, &sender2, None)
+        .unwrap_err();
+
+    assert_eq!(err,  ContractError::NoRectractableBid { });
+
+    let err = contract
+        .claim_fees(&mut app, &sender2)
+        .unwrap_err();
+
+    assert_eq!(err,  ContractError::Unauthorized { });
+
+    contract
+        .claim_fees(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
     assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), vec![]);
     assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(21, ATOM));
+
+    let err = contract
+        .claim_fees(&mut app, &owner)
+        .unwrap_err();
+
+    assert_eq!(err,  ContractError::NoFeesToClaim { });
 }
 
 #[test]
@@ -294,8 +386,8 @@ fn retract_to_friend() {
     assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), coins(10, ATOM));
     assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(5, ATOM));
     assert_eq!(app.wrap().query_all_balances(recipient.clone()).unwrap(), vec![]);
-    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(23, ATOM));
-    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(2, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(25, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), vec![]);
 
     contract
         .close(&mut app, &owner)
@@ -305,9 +397,2335 @@ fn retract_to_friend() {
         .retract(&mut app, &sender1, &recipient)
         .unwrap();
 
+    contract
+        .withdraw(&mut app, &recipient, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
     assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), coins(10, ATOM));
     assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(5, ATOM));
     assert_eq!(app.wrap().query_all_balances(recipient.clone()).unwrap(), coins(9, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(2, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(14, ATOM));
+
+    contract
+        .claim_fees(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
     assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), vec![]);
     assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(16, ATOM));
 }
+
+#[test]
+fn events() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender1, coins(20, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &sender2, coins(20, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(
+        &mut app,
+        contract_id,
+        &sender1,
+        &owner,
+        None,
+        None,
+        None,
+    ).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .make_bid(&mut app, &sender1, &coins(5, ATOM))
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-bid_placed")
+        .add_attribute("_contract_addr", contract.addr())
+        .add_attribute("bidder", &sender1)
+        .add_attribute("amount", "5atom")
+        .add_attribute("total", "15atom")
+        .add_attribute("commission", "0atom"));
+
+    contract
+        .make_bid(&mut app, &sender2, &coins(20, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-auction_closed")
+        .add_attribute("owner", &owner)
+        .add_attribute("winner", &sender2)
+        .add_attribute("bid", "20atom")
+        .add_attribute("payout", "18atom"));
+
+    let resp = contract
+        .retract(&mut app, &sender1, None)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-bid_retracted")
+        .add_attribute("bidder", &sender1)
+        .add_attribute("receiver", &sender1)
+        .add_attribute("amount", "14atom"));
+}
+
+#[test]
+fn empty_commodity() {
+    let owner = Addr::unchecked("owner");
+
+    let mut app = App::default();
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    // Empty attribute values are rejected, so the event leaves the commodity out.
+    let contract = BiddingContract::instantiate(&mut app, contract_id, &owner, None, None, "", None).unwrap();
+
+    assert_eq!(contract.query_config(&app).unwrap().config.commodity, "");
+}
+
+#[test]
+fn losing_bidders_refunded_in_full() {
+    let owner = Addr::unchecked("owner");
+    let treasury = Addr::unchecked("treasury");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender1, coins(20, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &sender2, coins(20, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            fee_recipient: Some(treasury.to_string()),
+            charge_losing_bidders: Some(false),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap();
+
+    contract
+        .make_bid(&mut app, &sender2, &coins(15, ATOM))
+        .unwrap();
+
+    let resp: FeesResponse = contract.query_fees(&app).unwrap();
+
+    assert_eq!(resp, FeesResponse { recipient: treasury.clone(), fees: vec![] });
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    let resp: FeesResponse = contract.query_fees(&app).unwrap();
+
+    assert_eq!(resp, FeesResponse { recipient: treasury.clone(), fees: coins(1, ATOM) });
+
+    contract
+        .retract(&mut app, &sender1, None)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &sender1, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
+    assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), coins(20, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(14, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(1, ATOM));
+
+    let err = contract
+        .claim_fees(&mut app, &owner)
+        .unwrap_err();
+
+    assert_eq!(err,  ContractError::Unauthorized { });
+
+    let resp = contract
+        .claim_fees(&mut app, &treasury)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &treasury, None)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-fees_claimed")
+        .add_attribute("recipient", &treasury)
+        .add_attribute("amount", "1atom"));
+
+    assert_eq!(app.wrap().query_all_balances(treasury.clone()).unwrap(), coins(1, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), vec![]);
+}
+
+#[test]
+fn multi_denom_bids() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender1, vec![coin(50, ATOM), coin(10, "btc"), coin(50, USDC)])
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &sender2, vec![coin(50, ATOM), coin(50, USDC)])
+            .unwrap();
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let err = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            denoms: vec![AcceptedDenom { denom: USDC.to_string(), weight: Decimal::percent(200) }],
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidDenoms {});
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            denoms: vec![
+                AcceptedDenom { denom: ATOM.to_string(), weight: Decimal::one() },
+                AcceptedDenom { denom: USDC.to_string(), weight: Decimal::percent(200) },
+            ],
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender1, &coins(10, "btc"))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::UnsupportedDenom { denom: "btc".to_string() });
+
+    contract
+        .make_bid(&mut app, &sender1, &[coin(10, ATOM), coin(5, USDC)])
+        .unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(10, USDC))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(20), required: Uint128::new(20) });
+
+    contract
+        .make_bid(&mut app, &sender2, &coins(15, USDC))
+        .unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(12, ATOM))
+        .unwrap();
+
+    let resp: BidsResponse = contract.query_bids(&app).unwrap();
+
+    assert_eq!(resp, BidsResponse { bids: vec![
+        Bid { address: sender1.clone(), coin: coin(32, ATOM) },
+        Bid { address: sender2.clone(), coin: coin(30, ATOM) },
+    ] });
+
+    let resp: EscrowResponse = contract.query_escrow(&app, &sender1).unwrap();
+
+    assert_eq!(resp, EscrowResponse { address: sender1.clone(), coins: vec![coin(22, ATOM), coin(5, USDC)] });
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .retract(&mut app, &sender2, None)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &sender2, None)
+        .unwrap();
+
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), vec![coin(20, ATOM), coin(5, USDC)]);
+    assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), vec![coin(28, ATOM), coin(10, "btc"), coin(45, USDC)]);
+    assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), vec![coin(50, ATOM), coin(49, USDC)]);
+
+    let resp: FeesResponse = contract.query_fees(&app).unwrap();
+
+    assert_eq!(resp, FeesResponse { recipient: owner.clone(), fees: vec![coin(2, ATOM), coin(1, USDC)] });
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), vec![coin(2, ATOM), coin(1, USDC)]);
+}
+
+#[test]
+fn weighted_bid_overflow() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, vec![coin(u128::MAX, ATOM), coin(u128::MAX, USDC)])
+            .unwrap();
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .denoms(vec![
+            AcceptedDenom { denom: ATOM.to_string(), weight: Decimal::one() },
+            AcceptedDenom { denom: USDC.to_string(), weight: Decimal::percent(200) },
+        ])
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender, &coins(u128::MAX / 2 + 1, USDC))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::Overflow {});
+
+    let err = contract
+        .make_bid(&mut app, &sender, &[coin(u128::MAX, ATOM), coin(1, USDC)])
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::Overflow {});
+
+    // Topping up a bid can overflow as well.
+    contract
+        .make_bid(&mut app, &sender, &coins(u128::MAX / 2, USDC))
+        .unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender, &coins(u128::MAX / 2, USDC))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::Overflow {});
+}
+
+#[test]
+fn allowlist() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+    let sender3 = Addr::unchecked("sender3");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender1, coins(20, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &sender2, coins(20, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            access_mode: Some(AccessMode::Allowlist),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidderNotAllowed {});
+
+    let err = contract
+        .update_access_list(&mut app, &sender1, &[&sender1], &[])
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    contract
+        .update_access_list(&mut app, &owner, &[&sender1, &sender2, &sender3], &[])
+        .unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap();
+
+    contract
+        .update_access_list(&mut app, &owner, &[], &[&sender2])
+        .unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(15, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidderNotAllowed {});
+
+    let resp: AccessListResponse = contract.query_access_list(&app, None, 1).unwrap();
+
+    assert_eq!(resp, AccessListResponse { mode: AccessMode::Allowlist, addresses: vec![sender1.clone()] });
+
+    let resp: AccessListResponse = contract.query_access_list(&app, &sender1, None).unwrap();
+
+    assert_eq!(resp, AccessListResponse { mode: AccessMode::Allowlist, addresses: vec![sender3.clone()] });
+}
+
+#[test]
+fn denylist() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender1, coins(20, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &sender2, coins(20, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            access_mode: Some(AccessMode::Denylist),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    let resp = contract
+        .update_access_list(&mut app, &owner, &[&sender2], &[])
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-access_list_updated")
+        .add_attribute("added", &sender2));
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(15, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidderNotAllowed {});
+}
+
+#[test]
+fn member_group() {
+    let owner = Addr::unchecked("owner");
+    let member = Addr::unchecked("member");
+    let light_member = Addr::unchecked("light_member");
+    let outsider = Addr::unchecked("outsider");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&member, &light_member, &outsider] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(20, "atom"))
+                .unwrap();
+        }
+    });
+
+    let group_id = Cw4GroupContract::store_code(&mut app);
+    let group = Cw4GroupContract::instantiate(
+        &mut app,
+        group_id,
+        &owner,
+        &[(&member, 5), (&light_member, 1)],
+    ).unwrap();
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            member_group: Some(MemberGroupMsg { contract: group.addr().to_string(), min_weight: Some(2) }),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &outsider, &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidderNotAllowed {});
+
+    let err = contract
+        .make_bid(&mut app, &light_member, &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidderNotAllowed {});
+
+    contract
+        .make_bid(&mut app, &member, &coins(10, ATOM))
+        .unwrap();
+
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(10, ATOM));
+}
+
+#[test]
+fn bid_caps() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender1, coins(50, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &sender2, coins(50, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            max_bid_per_address: Some(Uint128::new(30)),
+            max_total_escrow: Some(Uint128::new(45)),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(15, ATOM))
+        .unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender1, &coins(16, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidCapExceeded { cap: Uint128::new(30), attempted: Uint128::new(31) });
+
+    contract
+        .make_bid(&mut app, &sender2, &coins(20, ATOM))
+        .unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(8, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidCapExceeded { cap: Uint128::new(45), attempted: Uint128::new(53) });
+
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(45, ATOM));
+    assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(30, ATOM));
+
+    // A refund waiting to be withdrawn is no longer escrow and does not count towards the cap.
+    contract
+        .retract(&mut app, &sender2, None)
+        .unwrap();
+    contract
+        .make_bid(&mut app, &sender1, &coins(5, ATOM))
+        .unwrap();
+
+    assert_eq!(contract.query_claimable(&app, &sender2).unwrap().amount, coins(18, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(50, ATOM));
+}
+
+#[test]
+fn multi_unit_auction() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+    let sender3 = Addr::unchecked("sender3");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&sender1, &sender2, &sender3] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(100, "atom"))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "ticket".to_string(),
+            quantity: Some(10),
+            charge_losing_bidders: Some(false),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidUnitBid {});
+
+    let err = contract
+        .make_unit_bid(&mut app, &sender1, 11, 1, &coins(11, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidUnitBid {});
+
+    let err = contract
+        .make_unit_bid(&mut app, &sender1, 2, u128::MAX, &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidUnitBid {});
+
+    let err = contract
+        .make_unit_bid(&mut app, &sender1, 4, 10, &coins(30, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::UnitBidMismatch { required: Uint128::new(40), deposited: Uint128::new(30) });
+
+    contract
+        .make_unit_bid(&mut app, &sender1, 4, 10, &coins(40, ATOM))
+        .unwrap();
+
+    contract
+        .make_unit_bid(&mut app, &sender2, 5, 8, &coins(40, ATOM))
+        .unwrap();
+
+    contract
+        .make_unit_bid(&mut app, &sender3, 3, 5, &coins(15, ATOM))
+        .unwrap();
+
+    let resp: WinnersResponse = contract.query_winners(&app).unwrap();
+
+    assert_eq!(resp, WinnersResponse { winners: vec![
+        Allocation { address: sender1.clone(), units: 4, price: Uint128::new(5), lots: vec![] },
+        Allocation { address: sender2.clone(), units: 5, price: Uint128::new(5), lots: vec![] },
+        Allocation { address: sender3.clone(), units: 1, price: Uint128::new(5), lots: vec![] },
+    ] });
+
+    // Raising the unit price replaces the bid, topping up the escrow.
+    contract
+        .make_unit_bid(&mut app, &sender2, 6, 9, &coins(14, ATOM))
+        .unwrap();
+
+    let resp: WinnersResponse = contract.query_winners(&app).unwrap();
+
+    assert_eq!(resp, WinnersResponse { winners: vec![
+        Allocation { address: sender1.clone(), units: 4, price: Uint128::new(9), lots: vec![] },
+        Allocation { address: sender2.clone(), units: 6, price: Uint128::new(9), lots: vec![] },
+    ] });
+
+    let err = contract
+        .retract(&mut app, &sender1, None)
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::NoRectractableBid {});
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-units_allocated")
+        .add_attribute("bidder", &sender1)
+        .add_attribute("units", "4")
+        .add_attribute("price", "9")
+        .add_attribute("refund", "4atom"));
+
+    resp.assert_event(&Event::new("wasm-auction_closed")
+        .add_attribute("winner", format!("{},{}", sender1, sender2))
+        .add_attribute("bid", "90atom")
+        .add_attribute("payout", "82atom"));
+
+    contract
+        .retract(&mut app, &sender3, None)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &sender1, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &sender3, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
+    assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), coins(64, ATOM));
+    assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(46, ATOM));
+    assert_eq!(app.wrap().query_all_balances(sender3.clone()).unwrap(), coins(100, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(82, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), coins(8, ATOM));
+
+    let resp: FeesResponse = contract.query_fees(&app).unwrap();
+
+    assert_eq!(resp.fees, coins(8, ATOM));
+}
+
+#[test]
+fn unit_winners_pay_commission_on_allocation() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        for address in [&sender1, &sender2] {
+            router
+                .bank
+                .init_balance(storage, address, coins(100, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .quantity(10)
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_unit_bid(&mut app, &sender1, 4, 10, &coins(40, ATOM))
+        .unwrap();
+    contract
+        .make_unit_bid(&mut app, &sender2, 8, 5, &coins(40, ATOM))
+        .unwrap();
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    // Both were charged 4 while bidding, but owe commission on 4 and 6 units at 5 only.
+    assert_eq!(contract.query_fees(&app).unwrap().fees, coins(2 + 3, ATOM));
+    assert_eq!(contract.query_claimable(&app, &sender1).unwrap().amount, coins(20, ATOM));
+    assert_eq!(contract.query_claimable(&app, &sender2).unwrap().amount, coins(10, ATOM));
+    assert_eq!(contract.query_claimable(&app, &owner).unwrap().amount, coins(18 + 27, ATOM));
+}
+
+#[test]
+fn bundled_lots() {
+    let owner = Addr::unchecked("owner");
+    let senders: Vec<_> = ["sender1", "sender2", "sender3", "sender4", "sender5", "sender6"]
+        .into_iter()
+        .map(Addr::unchecked)
+        .collect();
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in &senders {
+            router
+                .bank
+                .init_balance(storage, sender, coins(50, "atom"))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "metals".to_string(),
+            lots: vec![
+                Lot { id: 1, commodity: "gold".to_string() },
+                Lot { id: 2, commodity: "silver".to_string() },
+                Lot { id: 3, commodity: "bronze".to_string() },
+            ],
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &senders[0], &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidLots {});
+
+    let err = contract
+        .make_bundle_bid(&mut app, &senders[0], &[1, 9], &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidLots {});
+
+    contract
+        .make_bundle_bid(&mut app, &senders[0], &[1], &coins(10, ATOM))
+        .unwrap();
+    contract
+        .make_bundle_bid(&mut app, &senders[1], &[2], &coins(12, ATOM))
+        .unwrap();
+    contract
+        .make_bundle_bid(&mut app, &senders[2], &[2, 1], &coins(20, ATOM))
+        .unwrap();
+    contract
+        .make_bundle_bid(&mut app, &senders[3], &[3], &coins(5, ATOM))
+        .unwrap();
+    contract
+        .make_bundle_bid(&mut app, &senders[4], &[1, 2, 3], &coins(30, ATOM))
+        .unwrap();
+
+    let resp: WinnersResponse = contract.query_winners(&app).unwrap();
+
+    assert_eq!(resp, WinnersResponse { winners: vec![
+        Allocation { address: senders[4].clone(), units: 1, price: Uint128::new(30), lots: vec![1, 2, 3] },
+    ] });
+
+    let err = contract
+        .make_bundle_bid(&mut app, &senders[5], &[1], &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(10), required: Uint128::new(10) });
+
+    let err = contract
+        .make_bundle_bid(&mut app, &senders[0], &[1, 3], &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidLots {});
+
+    contract
+        .make_bundle_bid(&mut app, &senders[5], &[1], &coins(15, ATOM))
+        .unwrap();
+
+    let resp: WinnersResponse = contract.query_winners(&app).unwrap();
+
+    assert_eq!(resp, WinnersResponse { winners: vec![
+        Allocation { address: senders[5].clone(), units: 1, price: Uint128::new(15), lots: vec![1] },
+        Allocation { address: senders[1].clone(), units: 1, price: Uint128::new(12), lots: vec![2] },
+        Allocation { address: senders[3].clone(), units: 1, price: Uint128::new(5), lots: vec![3] },
+    ] });
+
+    let err = contract
+        .retract(&mut app, &senders[5], None)
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::NoRectractableBid {});
+
+    contract
+        .retract(&mut app, &senders[4], None)
+        .unwrap();
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-lots_settled")
+        .add_attribute("winner", &senders[1])
+        .add_attribute("lots", "2")
+        .add_attribute("bid", "12")
+        .add_attribute("payout", "11atom"));
+
+    contract
+        .retract(&mut app, &senders[0], None)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &senders[0], None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &senders[4], None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(30, ATOM));
+    assert_eq!(app.wrap().query_all_balances(senders[0].clone()).unwrap(), coins(49, ATOM));
+    assert_eq!(app.wrap().query_all_balances(senders[4].clone()).unwrap(), coins(47, ATOM));
+    assert_eq!(app.wrap().query_all_balances(senders[5].clone()).unwrap(), coins(35, ATOM));
+}
+
+#[test]
+fn proxy_bids() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&sender1, &sender2] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(100, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            min_increment: Some(Uint128::new(1)),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    contract
+        .set_max_bid(&mut app, &sender1, 50, &coins(50, ATOM))
+        .unwrap();
+
+    let resp: EscrowResponse = contract.query_escrow(&app, &sender1).unwrap();
+
+    assert_eq!(resp.coins, coins(1, ATOM));
+
+    let resp = contract
+        .make_bid(&mut app, &sender2, &coins(10, ATOM))
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-bid_raised")
+        .add_attribute("bidder", &sender1)
+        .add_attribute("total", "12atom"));
+
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(2, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(12), required: Uint128::new(13) });
+
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(40, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(50), required: Uint128::new(50) });
+
+    contract
+        .make_bid(&mut app, &sender2, &coins(45, ATOM))
+        .unwrap();
+
+    let resp: BidsResponse = contract.query_bids(&app).unwrap();
+
+    assert_eq!(resp.bids[..2], [
+        Bid { address: sender2.clone(), coin: coin(55, ATOM) },
+        Bid { address: sender1.clone(), coin: coin(50, ATOM) },
+    ]);
+
+    let err = contract
+        .make_bid(&mut app, &sender1, &coins(30, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidProxyBid {});
+
+    let err = contract
+        .set_max_bid(&mut app, &sender1, 90, &coins(30, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidProxyBid {});
+
+    contract
+        .set_max_bid(&mut app, &sender1, 80, &coins(30, ATOM))
+        .unwrap();
+
+    let resp: BidsResponse = contract.query_bids(&app).unwrap();
+
+    assert_eq!(resp.bids[..2], [
+        Bid { address: sender1.clone(), coin: coin(57, ATOM) },
+        Bid { address: sender2.clone(), coin: coin(55, ATOM) },
+    ]);
+
+    contract
+        .retract(&mut app, &sender2, None)
+        .unwrap();
+
+    // The commission on the open proxy bid can still be partly refunded, so only the retracted
+    // bid's is claimable.
+    contract
+        .claim_fees(&mut app, &owner)
+        .unwrap();
+
+    let resp: FeesResponse = contract.query_fees(&app).unwrap();
+
+    assert_eq!(resp.fees, coins(8, ATOM));
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &sender1, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &sender2, None)
+        .unwrap();
+
+    // The winner only pays commission on the effective bid of 57, not on the max of 80.
+    let resp: FeesResponse = contract.query_fees(&app).unwrap();
+
+    assert_eq!(resp.fees, coins(5, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(52 + 5, ATOM));
+    assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), coins(43, ATOM));
+    assert_eq!(app.wrap().query_all_balances(sender2.clone()).unwrap(), coins(95, ATOM));
+}
+
+#[test]
+fn expiring_bids() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+    let sender3 = Addr::unchecked("sender3");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&sender1, &sender2, &sender3] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(50, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(
+        &mut app,
+        contract_id,
+        &owner,
+        None,
+        None,
+        "gold",
+        None,
+    ).unwrap();
+
+    let height = app.block_info().height;
+
+    let err = contract
+        .make_expiring_bid(&mut app, &sender1, Expiration::AtHeight(height), &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidExpiration {});
+
+    contract
+        .make_expiring_bid(&mut app, &sender1, Expiration::AtHeight(height + 5), &coins(10, ATOM))
+        .unwrap();
+
+    app.update_block(|block| block.height += 5);
+
+    let resp: BidsResponse = contract.query_bids(&app).unwrap();
+
+    assert_eq!(resp, BidsResponse { bids: vec![] });
+
+    contract
+        .make_bid(&mut app, &sender2, &coins(5, ATOM))
+        .unwrap();
+    contract
+        .make_expiring_bid(&mut app, &sender3, Expiration::AtHeight(height + 10), &coins(20, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .prune_expired(&mut app, &sender2, None)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-bid_expired")
+        .add_attribute("bidder", &sender1)
+        .add_attribute("refund", "9atom"));
+
+    let resp: EscrowResponse = contract.query_escrow(&app, &sender1).unwrap();
+
+    assert_eq!(resp.coins, vec![]);
+
+    let resp: WinnersResponse = contract.query_winners(&app).unwrap();
+
+    assert_eq!(resp, WinnersResponse { winners: vec![
+        Allocation { address: sender3.clone(), units: 1, price: Uint128::new(20), lots: vec![] },
+    ] });
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &sender1, None)
+        .unwrap();
+
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(18, ATOM));
+    assert_eq!(app.wrap().query_all_balances(sender1.clone()).unwrap(), coins(49, ATOM));
+}
+
+#[test]
+fn bid_on_behalf_of() {
+    let owner = Addr::unchecked("owner");
+    let dao = Addr::unchecked("dao");
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&owner, &dao, &alice, &bob] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(50, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(
+        &mut app,
+        contract_id,
+        &owner,
+        None,
+        None,
+        "gold",
+        None,
+    ).unwrap();
+
+    let err = contract
+        .make_bid_on_behalf_of(&mut app, &dao, &owner, &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::OwnerCannotBid {});
+
+    let err = contract
+        .make_bid_on_behalf_of(&mut app, &owner, &alice, &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::OwnerCannotBid {});
+
+    let resp = contract
+        .make_bid_on_behalf_of(&mut app, &dao, &alice, &coins(10, ATOM))
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-bid_placed")
+        .add_attribute("bidder", &alice)
+        .add_attribute("payer", &dao)
+        .add_attribute("total", "10atom"));
+
+    let err = contract
+        .make_bid(&mut app, &alice, &coins(5, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::PayerMismatch {});
+
+    contract
+        .make_bid(&mut app, &bob, &coins(15, ATOM))
+        .unwrap();
+
+    let err = contract
+        .retract_on_behalf_of(&mut app, &bob, &alice, None)
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let err = contract
+        .retract(&mut app, &alice, &alice)
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let resp = contract
+        .retract(&mut app, &alice, None)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &dao, None)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-bid_retracted")
+        .add_attribute("bidder", &alice)
+        .add_attribute("receiver", &dao)
+        .add_attribute("amount", "9atom"));
+
+    assert_eq!(app.wrap().query_all_balances(dao.clone()).unwrap(), coins(49, ATOM));
+    assert_eq!(app.wrap().query_all_balances(alice.clone()).unwrap(), coins(50, ATOM));
+}
+
+#[test]
+fn operators() {
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let bot = Addr::unchecked("bot");
+    let carol = Addr::unchecked("carol");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&alice, &bob] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(50, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(
+        &mut app,
+        contract_id,
+        &owner,
+        None,
+        None,
+        "gold",
+        None,
+    ).unwrap();
+
+    let height = app.block_info().height;
+
+    contract
+        .approve_operator(&mut app, &alice, &bot, None, 30, &coins(25, ATOM))
+        .unwrap();
+    contract
+        .approve_operator(&mut app, &alice, &carol, Expiration::AtHeight(height + 1), 30, &[])
+        .unwrap();
+    contract
+        .make_bid(&mut app, &alice, &coins(10, ATOM))
+        .unwrap();
+    contract
+        .make_bid(&mut app, &bob, &coins(15, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .raise_bid(&mut app, &bot, &alice, &coins(10, ATOM))
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-bid_placed")
+        .add_attribute("bidder", &alice)
+        .add_attribute("total", "20atom"));
+
+    contract
+        .make_bid(&mut app, &bob, &coins(10, ATOM))
+        .unwrap();
+
+    let err = contract
+        .raise_bid(&mut app, &bot, &alice, &coins(15, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidCapExceeded { cap: Uint128::new(30), attempted: Uint128::new(35) });
+
+    contract
+        .raise_bid(&mut app, &bot, &alice, &coins(8, ATOM))
+        .unwrap();
+
+    app.update_block(|block| block.height += 1);
+
+    let err = contract
+        .raise_bid(&mut app, &carol, &alice, &coins(1, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::OperatorNotApproved {});
+
+    let err = contract
+        .retract_on_behalf_of(&mut app, &carol, &alice, None)
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let err = contract
+        .retract_on_behalf_of(&mut app, &bot, &alice, None)
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::NoRectractableBid {});
+
+    let resp = contract
+        .revoke_operator(&mut app, &alice, &bot)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-operator_revoked")
+        .add_attribute("granter", &alice)
+        .add_attribute("operator", &bot)
+        .add_attribute("refund", "7atom"));
+
+    let resp = contract.query_approval(&app, &alice, &bot).unwrap();
+
+    assert_eq!(resp.approval, None);
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &alice, None)
+        .unwrap();
+
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(26, ATOM));
+    assert_eq!(app.wrap().query_all_balances(alice.clone()).unwrap(), coins(22, ATOM));
+}
+
+#[test]
+fn decrease_bid() {
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&alice, &bob] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(50, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            min_increment: Some(Uint128::new(2)),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    contract
+        .make_bid(&mut app, &bob, &coins(10, ATOM))
+        .unwrap();
+    contract
+        .make_bid(&mut app, &alice, &coins(20, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .decrease_bid(&mut app, &bob, &coins(4, ATOM))
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-bid_decreased")
+        .add_attribute("bidder", &bob)
+        .add_attribute("amount", "4atom")
+        .add_attribute("total", "6atom")
+        .add_attribute("refund", "3atom"));
+
+    let err = contract
+        .decrease_bid(&mut app, &bob, &coins(6, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::DecreaseToZero {});
+
+    let err = contract
+        .decrease_bid(&mut app, &alice, &coins(13, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(7), required: Uint128::new(8) });
+
+    contract
+        .decrease_bid(&mut app, &alice, &coins(11, ATOM))
+        .unwrap();
+
+    let resp: BidsResponse = contract.query_bids(&app).unwrap();
+
+    assert_eq!(resp.bids[..2], [
+        Bid { address: alice.clone(), coin: coin(9, ATOM) },
+        Bid { address: bob.clone(), coin: coin(6, ATOM) },
+    ]);
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+    contract
+        .retract(&mut app, &bob, None)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &alice, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &bob, None)
+        .unwrap();
+
+    let resp: FeesResponse = contract.query_fees(&app).unwrap();
+
+    assert_eq!(resp.fees, coins(3, ATOM));
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(9, ATOM));
+    assert_eq!(app.wrap().query_all_balances(alice.clone()).unwrap(), coins(39, ATOM));
+    assert_eq!(app.wrap().query_all_balances(bob.clone()).unwrap(), coins(49, ATOM));
+}
+
+#[test]
+fn starting_price() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&sender1, &sender2] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(50, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsg {
+            commodity: "gold".to_string(),
+            starting_price: Some(Uint128::new(10)),
+            ..InstantiateMsg::default()
+        },
+        &[],
+    ).unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender1, &coins(9, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BelowStartingPrice { amount: Uint128::new(9), starting_price: Uint128::new(10) });
+
+    contract
+        .set_max_bid(&mut app, &sender1, 30, &coins(30, ATOM))
+        .unwrap();
+
+    let resp: BidsResponse = contract.query_bids(&app).unwrap();
+
+    assert_eq!(resp, BidsResponse { bids: vec![Bid { address: sender1.clone(), coin: coin(10, ATOM) }] });
+
+    contract
+        .make_bid(&mut app, &sender2, &coins(40, ATOM))
+        .unwrap();
+
+    let err = contract
+        .decrease_bid(&mut app, &sender2, &coins(35, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BelowStartingPrice { amount: Uint128::new(5), starting_price: Uint128::new(10) });
+}
+
+#[test]
+fn huge_min_increment() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&sender1, &sender2] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(50, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate_with_msg(
+        &mut app,
+        contract_id,
+        &owner,
+        &InstantiateMsgBuilder::new("gold").min_increment(u128::MAX).build(),
+        &[],
+    ).unwrap();
+
+    contract
+        .set_max_bid(&mut app, &sender1, 30, &coins(30, ATOM))
+        .unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(40, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(40), required: Uint128::MAX });
+
+    let err = contract
+        .set_max_bid(&mut app, &sender2, 40, &coins(40, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(40), required: Uint128::MAX });
+}
+
+#[test]
+fn close_without_bids() {
+    let owner = Addr::unchecked("owner");
+
+    let mut app = App::default();
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(
+        &mut app,
+        contract_id,
+        &owner,
+        None,
+        None,
+        "gold",
+        None,
+    ).unwrap();
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-auction_closed")
+        .add_attribute("owner", &owner)
+        .add_attribute("bid", "0atom"));
+
+    let resp: WinnersResponse = contract.query_winners(&app).unwrap();
+
+    assert_eq!(resp, WinnersResponse { winners: vec![] });
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), vec![]);
+}
+
+#[test]
+fn close_with_only_owner_deposit() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        for address in [&owner, &sender] {
+            router
+                .bank
+                .init_balance(storage, address, coins(10, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(
+        &mut app,
+        contract_id,
+        &owner,
+        None,
+        None,
+        "gold",
+        10,
+    ).unwrap();
+
+    let err = contract
+        .make_bid(&mut app, &sender, &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(10), required: Uint128::new(10) });
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-auction_closed")
+        .add_attribute("owner", &owner)
+        .add_attribute("bid", "0atom")
+        .add_attribute("payout", "10atom"));
+
+    let resp: FeesResponse = contract.query_fees(&app).unwrap();
+
+    assert_eq!(resp.fees, vec![]);
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(10, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), vec![]);
+}
+
+#[test]
+fn close_with_owner_deposit_outbid() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &owner, coins(10, ATOM))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &sender, coins(20, ATOM))
+            .unwrap();
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(
+        &mut app,
+        contract_id,
+        &owner,
+        None,
+        None,
+        "gold",
+        10,
+    ).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender, &coins(15, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-auction_closed")
+        .add_attribute("winner", &sender)
+        .add_attribute("bid", "15atom")
+        .add_attribute("payout", "24atom"));
+
+    contract
+        .claim_fees(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
+    assert_eq!(app.wrap().query_all_balances(owner.clone()).unwrap(), coins(25, ATOM));
+    assert_eq!(app.wrap().query_all_balances(sender.clone()).unwrap(), coins(5, ATOM));
+    assert_eq!(app.wrap().query_all_balances(contract.addr()).unwrap(), vec![]);
+}
+
+#[test]
+fn owner_deposit_floor() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        for address in [&owner, &sender] {
+            router
+                .bank
+                .init_balance(storage, address, coins(100, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(&mut app, contract_id, &owner, None, None, "gold", 10).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender, &coins(12, ATOM))
+        .unwrap();
+
+    // A sole leader cannot decrease below the deposit either.
+    let err = contract
+        .decrease_bid(&mut app, &sender, &coins(2, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(10), required: Uint128::new(10) });
+
+    contract
+        .decrease_bid(&mut app, &sender, &coins(1, ATOM))
+        .unwrap();
+
+    // Multi-unit bids have to beat it with their unit price.
+    let msg = InstantiateMsgBuilder::new("gold")
+        .quantity(5)
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &coins(10, ATOM)).unwrap();
+
+    let err = contract
+        .make_unit_bid(&mut app, &sender, 2, 10, &coins(20, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(10), required: Uint128::new(10) });
+
+    contract
+        .make_unit_bid(&mut app, &sender, 2, 11, &coins(22, ATOM))
+        .unwrap();
+
+    // Bundle bids have to beat it with their whole bid.
+    let msg = InstantiateMsgBuilder::new("gold")
+        .lots(vec![
+            Lot { id: 1, commodity: "gold".to_string() },
+            Lot { id: 2, commodity: "silver".to_string() },
+        ])
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &coins(10, ATOM)).unwrap();
+
+    let err = contract
+        .make_bundle_bid(&mut app, &sender, &[1], &coins(10, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidTooLow { amount: Uint128::new(10), required: Uint128::new(10) });
+
+    contract
+        .make_bundle_bid(&mut app, &sender, &[1], &coins(11, ATOM))
+        .unwrap();
+}
+
+#[test]
+fn testing_helpers() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = BiddingContract::app_with_funds(sender.clone(), 100);
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .owner(owner.as_str())
+        .min_increment(5)
+        .starting_price(10)
+        .build();
+
+    assert_eq!(msg, InstantiateMsg {
+        commodity: "gold".to_string(),
+        owner: Some(owner.to_string()),
+        min_increment: Some(Uint128::new(5)),
+        starting_price: Some(Uint128::new(10)),
+        ..InstantiateMsg::default()
+    });
+
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender, &coins(20, ATOM))
+        .unwrap();
+
+    assert_bids(&app, &contract, &[(&sender, 20)]);
+    assert_balance(&app, &sender, 80, ATOM);
+    assert_balance(&app, contract.addr(), 20, ATOM);
+}
+
+#[test]
+fn contract_client() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = BiddingContract::app_with_funds(sender.clone(), 100);
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(&mut app, contract_id, &owner, None, None, "gold", None).unwrap();
+
+    let client = BiddingContractClient(contract.addr().clone());
+
+    app.execute(sender.clone(), client.make_bid(coins(20, ATOM)).unwrap())
+        .unwrap();
+
+    let resp = client.query_bids(&app.wrap()).unwrap();
+
+    assert_eq!(resp, BidsResponse { bids: vec![Bid { address: sender.clone(), coin: coin(20, ATOM) }] });
+
+    app.execute(owner.clone(), client.close().unwrap())
+        .unwrap();
+
+    let resp = client.query_winners(&app.wrap()).unwrap();
+
+    assert_eq!(resp.winners.len(), 1);
+    assert_eq!(resp.winners[0].address, sender);
+
+    let resp = client.query_claimable(&app.wrap(), &owner).unwrap();
+
+    assert_eq!(resp.amount, coins(18, ATOM));
+
+    app.execute(owner.clone(), client.withdraw(None).unwrap())
+        .unwrap();
+
+    assert_eq!(app.wrap().query_balance(&owner, ATOM).unwrap().amount.u128(), 18);
+}
+
+#[test]
+fn settlement_hook() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = BiddingContract::app_with_funds(sender.clone(), 100);
+
+    let contract_id = BiddingContract::store_code(&mut app);
+    let hook_id = HookContract::store_code(&mut app);
+
+    let hook = HookContract::instantiate(&mut app, hook_id, &owner, HookBehavior::Record).unwrap();
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .settlement_hook(hook.addr().as_str())
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender, &coins(20, ATOM))
+        .unwrap();
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    assert_eq!(hook.query_received(&app).unwrap(), vec![HookMsg::Settlement(SettlementHookMsg::AuctionSettled {
+        winner: sender.clone(),
+        price: coin(20, ATOM),
+        commodity: "gold".to_string(),
+    })]);
+    assert_eq!(contract.query_failed_settlements(&app).unwrap(), FailedSettlementsResponse { failures: vec![] });
+}
+
+#[test]
+fn failing_settlement_hook() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = BiddingContract::app_with_funds(sender.clone(), 100);
+
+    let contract_id = BiddingContract::store_code(&mut app);
+    let hook_id = HookContract::store_code(&mut app);
+
+    let hook = HookContract::instantiate(&mut app, hook_id, &owner, HookBehavior::Fail).unwrap();
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .settlement_hook(hook.addr().as_str())
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender, &coins(20, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    assert!(resp.has_event(&Event::new("wasm-settlement_hook_failed").add_attribute("winner", sender.as_str())));
+
+    // The close went through and credited the owner.
+    assert_eq!(contract.query_claimable(&app, &owner).unwrap().amount, coins(18, ATOM));
+
+    let resp = contract.query_failed_settlements(&app).unwrap();
+
+    assert_eq!(resp.failures.len(), 1);
+    assert_eq!(resp.failures[0].winner, sender);
+    assert!(!resp.failures[0].error.is_empty());
+}
+
+#[test]
+fn settlement_hook_out_of_gas() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = BiddingContract::app_with_funds(sender.clone(), 100);
+
+    let contract_id = BiddingContract::store_code(&mut app);
+    let hook_id = HookContract::store_code(&mut app);
+
+    let hook = HookContract::instantiate(&mut app, hook_id, &owner, HookBehavior::RunOutOfGas).unwrap();
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .settlement_hook(hook.addr().as_str())
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender, &coins(20, ATOM))
+        .unwrap();
+
+    // With the gas limit, running out only fails the hook and the close goes through.
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    let resp = contract.query_failed_settlements(&app).unwrap();
+
+    assert_eq!(resp.failures.len(), 1);
+    assert_eq!(resp.failures[0].winner, sender);
+    assert_eq!(contract.query_claimable(&app, &owner).unwrap().amount, coins(18, ATOM));
+}
+
+#[test]
+fn outbid_hooks() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+    let sender3 = Addr::unchecked("sender3");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&sender1, &sender2, &sender3] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(100, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+    let hook_id = HookContract::store_code(&mut app);
+
+    let hook = HookContract::instantiate(&mut app, hook_id, &owner, HookBehavior::Record).unwrap();
+    let failing_hook = HookContract::instantiate(&mut app, hook_id, &owner, HookBehavior::Fail).unwrap();
+
+    let contract = BiddingContract::instantiate(&mut app, contract_id, &owner, None, None, "gold", None).unwrap();
+
+    contract
+        .register_outbid_hook(&mut app, &sender1, hook.addr())
+        .unwrap();
+    contract
+        .register_outbid_hook(&mut app, &sender2, failing_hook.addr())
+        .unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap();
+
+    // Topping up the leading bid outbids no one.
+    contract
+        .make_bid(&mut app, &sender1, &coins(5, ATOM))
+        .unwrap();
+
+    assert_eq!(hook.query_received(&app).unwrap(), vec![]);
+
+    contract
+        .make_bid(&mut app, &sender2, &coins(20, ATOM))
+        .unwrap();
+
+    assert_eq!(hook.query_received(&app).unwrap(), vec![HookMsg::Outbid(OutbidHookMsg::Outbid {
+        bidder: sender1.clone(),
+        commodity: "gold".to_string(),
+    })]);
+
+    // The failing hook does not stop the bid that outbid its bidder.
+    let resp = contract
+        .make_bid(&mut app, &sender3, &coins(30, ATOM))
+        .unwrap();
+
+    assert!(resp.events.iter().any(|event| event.ty == "wasm-outbid_hook_failed"));
+
+    let resp = contract.query_bids(&app).unwrap();
+
+    assert_eq!(resp.bids[0], Bid { address: sender3.clone(), coin: coin(30, ATOM) });
+}
+
+#[test]
+fn outbid_hook_out_of_gas() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        for sender in [&sender1, &sender2] {
+            router
+                .bank
+                .init_balance(storage, sender, coins(100, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+    let hook_id = HookContract::store_code(&mut app);
+
+    let hook = HookContract::instantiate(&mut app, hook_id, &owner, HookBehavior::RunOutOfGas).unwrap();
+
+    let contract = BiddingContract::instantiate(&mut app, contract_id, &owner, None, None, "gold", None).unwrap();
+
+    contract
+        .register_outbid_hook(&mut app, &sender1, hook.addr())
+        .unwrap();
+    contract
+        .make_bid(&mut app, &sender1, &coins(10, ATOM))
+        .unwrap();
+
+    // With the gas limit, running out only fails the hook and the leader is still outbid.
+    let resp = contract
+        .make_bid(&mut app, &sender2, &coins(20, ATOM))
+        .unwrap();
+
+    assert!(resp.events.iter().any(|event| event.ty == "wasm-outbid_hook_failed"));
+
+    let resp = contract.query_bids(&app).unwrap();
+
+    assert_eq!(resp.bids[0], Bid { address: sender2.clone(), coin: coin(20, ATOM) });
+}
+
+#[test]
+fn royalties() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let creator1 = Addr::unchecked("creator1");
+    let creator2 = Addr::unchecked("creator2");
+
+    let mut app = BiddingContract::app_with_funds(sender.clone(), 200);
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .royalty(creator1.as_str(), Decimal::percent(90))
+        .royalty(creator2.as_str(), Decimal::permille(1))
+        .build();
+    let err = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap_err();
+
+    assert_eq!(err, ContractError::InvalidRoyalties {});
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .royalty(creator1.as_str(), Decimal::percent(5))
+        .royalty(creator2.as_str(), Decimal::permille(25))
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender, &coins(101, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &creator1, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &creator2, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
+    assert!(resp.has_event(&Event::new("wasm-royalty_paid")
+        .add_attribute("recipient", creator1.as_str())
+        .add_attribute("amount", "5atom")));
+
+    // 101 less 10 commission and 5 + 2 royalties; the rounding dust stays with the owner.
+    assert_balance(&app, &creator1, 5, ATOM);
+    assert_balance(&app, &creator2, 2, ATOM);
+    assert_balance(&app, &owner, 84, ATOM);
+    assert_balance(&app, contract.addr(), 10, ATOM);
+}
+
+#[test]
+fn vault_yield_to_owner() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        for (address, amount) in [(&sender1, 100), (&sender2, 100), (&owner, 10)] {
+            router
+                .bank
+                .init_balance(storage, address, coins(amount, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+    let vault_id = VaultContract::store_code(&mut app);
+
+    let vault = VaultContract::instantiate(&mut app, vault_id, &owner).unwrap();
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .vault(vault.addr().as_str(), YieldRecipient::Owner)
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(20, ATOM))
+        .unwrap();
+    contract
+        .make_bid(&mut app, &sender2, &coins(30, ATOM))
+        .unwrap();
+
+    // Only the commission stays in the contract.
+    assert_eq!(vault.query_balance(&app, contract.addr()).unwrap().amount, coins(45, ATOM));
+    assert_balance(&app, contract.addr(), 5, ATOM);
+
+    contract
+        .retract(&mut app, &sender1, None)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &sender1, None)
+        .unwrap();
+
+    assert_eq!(vault.query_balance(&app, contract.addr()).unwrap().amount, coins(27, ATOM));
+    assert_balance(&app, &sender1, 98, ATOM);
+
+    vault
+        .accrue(&mut app, &owner, contract.addr(), &coins(6, ATOM))
+        .unwrap();
+
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+
+    assert!(resp.has_event(&Event::new("wasm-vault_yield")
+        .add_attribute("amount", "6atom")
+        .add_attribute("to_owner", "6atom")));
+
+    assert_eq!(vault.query_balance(&app, contract.addr()).unwrap().amount, vec![]);
+    assert_balance(&app, &owner, 4 + 27 + 6, ATOM);
+    assert_balance(&app, &sender2, 70, ATOM);
+    assert_balance(&app, contract.addr(), 5, ATOM);
+    assert_balance(&app, vault.addr(), 0, ATOM);
+}
+
+#[test]
+fn vault_bid_caps() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        for address in [&sender1, &sender2] {
+            router
+                .bank
+                .init_balance(storage, address, coins(100, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+    let vault_id = VaultContract::store_code(&mut app);
+
+    let vault = VaultContract::instantiate(&mut app, vault_id, &owner).unwrap();
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .max_total_escrow(100)
+        .vault(vault.addr().as_str(), YieldRecipient::Owner)
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(60, ATOM))
+        .unwrap();
+
+    // The escrow in the vault counts towards the cap as well.
+    let err = contract
+        .make_bid(&mut app, &sender2, &coins(70, ATOM))
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::BidCapExceeded { cap: Uint128::new(100), attempted: Uint128::new(130) });
+}
+
+#[test]
+fn vault_yield_to_bidders() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+
+    let mut app = App::new(|router, _api, storage| {
+        for (address, amount) in [(&sender1, 100), (&sender2, 100), (&owner, 10)] {
+            router
+                .bank
+                .init_balance(storage, address, coins(amount, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+    let vault_id = VaultContract::store_code(&mut app);
+
+    let vault = VaultContract::instantiate(&mut app, vault_id, &owner).unwrap();
+
+    let msg = InstantiateMsgBuilder::new("gold")
+        .vault(vault.addr().as_str(), YieldRecipient::Bidders)
+        .build();
+    let contract = BiddingContract::instantiate_with_msg(&mut app, contract_id, &owner, &msg, &[]).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(20, ATOM))
+        .unwrap();
+    contract
+        .make_bid(&mut app, &sender2, &coins(30, ATOM))
+        .unwrap();
+
+    vault
+        .accrue(&mut app, &owner, contract.addr(), &coins(9, ATOM))
+        .unwrap();
+
+    // Shared 18:27 and rounded down, the dust going to the owner.
+    let resp = contract
+        .close(&mut app, &owner)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap();
+    contract
+        .withdraw(&mut app, &sender2, None)
+        .unwrap();
+
+    assert!(resp.has_event(&Event::new("wasm-vault_yield")
+        .add_attribute("amount", "9atom")
+        .add_attribute("to_owner", "1atom")));
+
+    assert_balance(&app, &owner, 1 + 27 + 1, ATOM);
+    assert_balance(&app, &sender2, 70 + 5, ATOM);
+
+    contract
+        .retract(&mut app, &sender1, None)
+        .unwrap();
+
+    contract
+        .withdraw(&mut app, &sender1, None)
+        .unwrap();
+
+    assert_balance(&app, &sender1, 80 + 18 + 3, ATOM);
+    assert_balance(&app, contract.addr(), 5, ATOM);
+}
+
+#[test]
+fn withdraw_claimable() {
+    let owner = Addr::unchecked("owner");
+    let sender1 = Addr::unchecked("sender1");
+    let sender2 = Addr::unchecked("sender2");
+    let cold_wallet = Addr::unchecked("cold_wallet");
+
+    let mut app = App::new(|router, _api, storage| {
+        for address in [&sender1, &sender2] {
+            router
+                .bank
+                .init_balance(storage, address, coins(100, ATOM))
+                .unwrap();
+        }
+    });
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(&mut app, contract_id, &owner, None, None, "gold", None).unwrap();
+
+    contract
+        .make_bid(&mut app, &sender1, &coins(20, ATOM))
+        .unwrap();
+    contract
+        .make_bid(&mut app, &sender2, &coins(30, ATOM))
+        .unwrap();
+
+    let err = contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::NothingToWithdraw {});
+
+    contract
+        .close(&mut app, &owner)
+        .unwrap();
+    contract
+        .retract(&mut app, &sender1, None)
+        .unwrap();
+
+    // Nothing is sent until it is withdrawn.
+    assert_balance(&app, &owner, 0, ATOM);
+    assert_balance(&app, &sender1, 80, ATOM);
+    assert_eq!(contract.query_claimable(&app, &owner).unwrap().amount, coins(27, ATOM));
+    assert_eq!(contract.query_claimable(&app, &sender1).unwrap().amount, coins(18, ATOM));
+    assert_eq!(contract.query_accounting(&app).unwrap().claimable, coins(45, ATOM));
+
+    let resp = contract
+        .withdraw(&mut app, &owner, &cold_wallet)
+        .unwrap();
+
+    resp.assert_event(&Event::new("wasm-withdrawn")
+        .add_attribute("address", &owner)
+        .add_attribute("receiver", &cold_wallet)
+        .add_attribute("amount", "27atom"));
+
+    contract
+        .withdraw(&mut app, &sender1, None)
+        .unwrap();
+
+    assert_balance(&app, &cold_wallet, 27, ATOM);
+    assert_balance(&app, &sender1, 98, ATOM);
+    assert_eq!(contract.query_claimable(&app, &owner).unwrap().amount, vec![]);
+
+    let err = contract
+        .withdraw(&mut app, &owner, None)
+        .unwrap_err();
+
+    assert_eq!(err, ContractError::NothingToWithdraw {});
+}
+
+#[test]
+fn funds_rejected() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = BiddingContract::app_with_funds(sender.clone(), 100);
+
+    let contract_id = BiddingContract::store_code(&mut app);
+
+    let contract = BiddingContract::instantiate(&mut app, contract_id, &owner, None, None, "gold", None).unwrap();
+
+    let msgs = [
+        ExecuteMsg::Close {},
+        ExecuteMsg::Retract { receiver: None, on_behalf_of: None },
+        ExecuteMsg::DecreaseBid { amount: coins(1, ATOM) },
+        ExecuteMsg::ClaimFees {},
+        ExecuteMsg::RevokeOperator { operator: owner.to_string() },
+        ExecuteMsg::RaiseBid { granter: owner.to_string(), amount: coins(1, ATOM) },
+        ExecuteMsg::PruneExpired { limit: None },
+        ExecuteMsg::UpdateAccessList { add: vec![], remove: vec![] },
+        ExecuteMsg::RegisterOutbidHook { contract: owner.to_string() },
+        ExecuteMsg::Withdraw { receiver: None },
+    ];
+    for msg in msgs {
+        let err: ContractError = app
+            .execute_contract(sender.clone(), contract.addr().clone(), &msg, &coins(10, ATOM))
+            .unwrap_err()
+            .downcast()
+            .unwrap();
+
+        assert_eq!(err, ContractError::Payment(PaymentError::NonPayable {}));
+    }
+
+    assert_balance(&app, &sender, 100, ATOM);
+}
@@ -1,5 +1,6 @@
-use cosmwasm_std::{Addr, Coin};
+use cosmwasm_std::{Addr, Coin, Decimal, Empty, Uint128};
 use cw_storage_plus::{Item, Map};
+use cw_utils::Expiration;
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
 
@@ -7,7 +8,161 @@ use schemars::JsonSchema;
 pub struct Config {
     pub owner: Addr,
     pub commodity: String,
+    pub fee_recipient: Addr,
+    pub charge_losing_bidders: bool,
+    /// Denoms a bid can be paid in. The first one is the base denom bids are compared in.
+    pub denoms: Vec<AcceptedDenom>,
+    pub access_mode: AccessMode,
+    /// When set, only members of this cw4 group can bid.
+    pub member_group: Option<MemberGroup>,
+    /// Highest total bid a single address can place, valued in the base denom.
+    pub max_bid_per_address: Option<Uint128>,
+    /// Highest total the escrowed bids can reach, valued in the base denom.
+    pub max_total_escrow: Option<Uint128>,
+    /// Number of identical units sold in a multi-unit auction; `None` sells the commodity as a
+    /// single lot.
+    pub quantity: Option<u64>,
+    /// Lots sold separately or in bundles; empty sells the commodity as a single lot.
+    pub lots: Vec<Lot>,
+    /// Bids have to exceed the leading bid by more than this.
+    pub min_increment: Uint128,
+    /// Lowest opening bid, and lowest unit price in a multi-unit auction.
+    pub starting_price: Uint128,
+    /// Contract sent `AuctionSettled` for every winner on close.
+    pub settlement_hook: Option<Addr>,
+    /// Shares of the winning amount paid to creators on close, next to the commission.
+    #[serde(default)]
+    pub royalties: Vec<Royalty>,
+    /// When set, escrowed bids are deposited in this vault while the auction runs.
+    pub vault: Option<Vault>,
+}
+
+impl Config {
+    pub fn base_denom(&self) -> &str {
+        &self.denoms[0].denom
+    }
+}
+
+/// A bid of `amount` in `denom` is worth `amount * weight` of the base denom.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AcceptedDenom {
+    pub denom: String,
+    pub weight: Decimal,
+}
+
+/// Who may bid. The addresses the allow- or denylist applies to are kept in `ACCESS_LIST`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessMode {
+    Open,
+    Allowlist,
+    Denylist,
+}
+
+impl Default for AccessMode {
+    fn default() -> Self {
+        AccessMode::Open
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Royalty {
+    pub address: Addr,
+    pub share: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Vault {
+    /// Contract implementing `VaultExecuteMsg` and `VaultQueryMsg`.
+    pub contract: Addr,
+    pub yield_recipient: YieldRecipient,
+}
+
+/// Who gets the yield the vault accrued, paid out on close.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum YieldRecipient {
+    /// Bids still escrowed on close, pro rata; the rounding dust goes to the owner.
+    Bidders,
+    Owner,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MemberGroup {
+    /// Address of a cw4 group contract.
+    pub contract: Addr,
+    /// Members below this weight cannot bid. Defaults to any member, including zero weight.
+    pub min_weight: Option<u64>,
+}
+
+/// What an operator may do for the granter until `expires`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Approval {
+    pub expires: Expiration,
+    /// Highest total bid the operator can raise the granter's bid to.
+    pub max_amount: Uint128,
+    /// Funds the granter set aside for the operator to raise bids with.
+    pub reserve: Vec<Coin>,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const BIDS: Map<Addr, Coin> = Map::new("bids");
+/// When bids stop counting; bids without an entry never expire.
+pub const EXPIRIES: Map<Addr, Expiration> = Map::new("expiries");
+/// Who funded bids placed on behalf of someone else; refunds go to them.
+pub const PAYERS: Map<Addr, Addr> = Map::new("payers");
+/// Approvals keyed by granter and operator.
+pub const OPERATORS: Map<(Addr, Addr), Approval> = Map::new("operators");
+/// Maximum of each proxy bidder; their effective bid is kept in `BIDS`.
+pub const PROXY_BIDS: Map<Addr, Uint128> = Map::new("proxy_bids");
+pub const CLOSED: Item<bool> = Item::new("closed");
+/// Funds the owner sent on instantiation. Bids have to exceed their value, and they go back to
+/// the owner on close.
+pub const OWNER_DEPOSIT: Item<Vec<Coin>> = Item::new("owner_deposit");
+/// Commission accrued and not yet claimed by the fee recipient.
+pub const FEES: Item<Vec<Coin>> = Item::new("fees");
+/// Payouts credited to each address and not withdrawn yet.
+pub const CLAIMABLE: Map<Addr, Vec<Coin>> = Map::new("claimable");
+/// What was deposited in the vault and not withdrawn yet, without the yield.
+pub const VAULT_PRINCIPAL: Item<Vec<Coin>> = Item::new("vault_principal");
+/// Coins deposited per bidder; `BIDS` holds their value in the base denom.
+pub const ESCROW: Map<Addr, Vec<Coin>> = Map::new("escrow");
+/// Commission already charged per bidder, kept back from their refund.
+pub const COMMISSIONS: Map<Addr, Vec<Coin>> = Map::new("commissions");
+pub const ACCESS_LIST: Map<Addr, Empty> = Map::new("access_list");
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Lot {
+    pub id: u32,
+    pub commodity: String,
+}
+
+/// Units and per-unit price asked for by a bidder in a multi-unit auction.
+pub const UNIT_BIDS: Map<Addr, UnitBid> = Map::new("unit_bids");
+/// Lot ids a bidder's bid is on when lots are bundled.
+pub const BUNDLE_BIDS: Map<Addr, Vec<u32>> = Map::new("bundle_bids");
+/// Winners settled on close.
+pub const WINNERS: Map<Addr, Allocation> = Map::new("winners");
+/// Contract each bidder wants notified when they are outbid.
+pub const OUTBID_HOOKS: Map<Addr, Addr> = Map::new("outbid_hooks");
+/// Winner each settlement hook sent on close was for, keyed by the hook's reply id.
+pub const SETTLEMENT_HOOKS: Map<u64, Addr> = Map::new("settlement_hooks");
+/// Error of every settlement hook that failed, keyed by the winner it was for.
+pub const FAILED_SETTLEMENTS: Map<Addr, String> = Map::new("failed_settlements");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UnitBid {
+    pub units: u64,
+    pub price: Uint128,
+}
+
+/// Units won and the price paid per unit. A single lot or bundle is one unit priced at the
+/// winning bid.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Allocation {
+    pub address: Addr,
+    pub units: u64,
+    pub price: Uint128,
+    /// Lot ids won, only set when lots are bundled.
+    #[serde(default)]
+    pub lots: Vec<u32>,
+}
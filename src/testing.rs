@@ -0,0 +1,9 @@
+//! Multitest helpers for spinning up an auction in other contracts' tests. Built with the
+//! `testing` feature.
+mod assertions;
+mod builders;
+mod contract;
+
+pub use assertions::{assert_balance, assert_bids};
+pub use builders::InstantiateMsgBuilder;
+pub use contract::BiddingContract;
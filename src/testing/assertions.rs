@@ -0,0 +1,28 @@
+use cosmwasm_std::Addr;
+use cw_multi_test::App;
+
+use super::contract::BiddingContract;
+
+/// Asserts `address` holds exactly `amount` of `denom`.
+#[track_caller]
+pub fn assert_balance(app: &App, address: &Addr, amount: u128, denom: &str) {
+    let balance = app.wrap().query_balance(address, denom).unwrap();
+    assert_eq!(balance.amount.u128(), amount, "balance of {} in {}", address, denom);
+}
+
+/// Asserts the open bids, valued in the base denom, are exactly `expected`, in any order.
+#[track_caller]
+pub fn assert_bids(app: &App, contract: &BiddingContract, expected: &[(&Addr, u128)]) {
+    let mut bids: Vec<_> = contract.query_bids(app).unwrap().bids
+        .into_iter()
+        .map(|bid| (bid.address, bid.coin.amount.u128()))
+        .collect();
+    bids.sort();
+
+    let mut expected: Vec<_> = expected.iter()
+        .map(|(address, amount)| ((*address).clone(), *amount))
+        .collect();
+    expected.sort();
+
+    assert_eq!(bids, expected);
+}
@@ -0,0 +1,102 @@
+use cosmwasm_std::{Decimal, Uint128};
+
+use crate::msg::{InstantiateMsg, MemberGroupMsg, RoyaltyMsg, VaultMsg};
+use crate::state::{AcceptedDenom, AccessMode, Lot, YieldRecipient};
+
+/// Builds an [`InstantiateMsg`], leaving everything not set to the contract's defaults.
+#[derive(Clone, Debug, Default)]
+pub struct InstantiateMsgBuilder(InstantiateMsg);
+
+impl InstantiateMsgBuilder {
+    pub fn new(commodity: impl Into<String>) -> Self {
+        InstantiateMsgBuilder(InstantiateMsg {
+            commodity: commodity.into(),
+            ..InstantiateMsg::default()
+        })
+    }
+
+    pub fn owner(mut self, owner: impl Into<String>) -> Self {
+        self.0.owner = Some(owner.into());
+        self
+    }
+
+    pub fn fee_recipient(mut self, fee_recipient: impl Into<String>) -> Self {
+        self.0.fee_recipient = Some(fee_recipient.into());
+        self
+    }
+
+    pub fn charge_losing_bidders(mut self, charge: bool) -> Self {
+        self.0.charge_losing_bidders = Some(charge);
+        self
+    }
+
+    pub fn denoms(mut self, denoms: Vec<AcceptedDenom>) -> Self {
+        self.0.denoms = denoms;
+        self
+    }
+
+    pub fn access_mode(mut self, access_mode: AccessMode) -> Self {
+        self.0.access_mode = Some(access_mode);
+        self
+    }
+
+    pub fn member_group(mut self, contract: impl Into<String>, min_weight: impl Into<Option<u64>>) -> Self {
+        self.0.member_group = Some(MemberGroupMsg { contract: contract.into(), min_weight: min_weight.into() });
+        self
+    }
+
+    pub fn max_bid_per_address(mut self, max: u128) -> Self {
+        self.0.max_bid_per_address = Some(Uint128::new(max));
+        self
+    }
+
+    pub fn max_total_escrow(mut self, max: u128) -> Self {
+        self.0.max_total_escrow = Some(Uint128::new(max));
+        self
+    }
+
+    pub fn quantity(mut self, quantity: u64) -> Self {
+        self.0.quantity = Some(quantity);
+        self
+    }
+
+    pub fn lots(mut self, lots: Vec<Lot>) -> Self {
+        self.0.lots = lots;
+        self
+    }
+
+    pub fn min_increment(mut self, min_increment: u128) -> Self {
+        self.0.min_increment = Some(Uint128::new(min_increment));
+        self
+    }
+
+    pub fn starting_price(mut self, starting_price: u128) -> Self {
+        self.0.starting_price = Some(Uint128::new(starting_price));
+        self
+    }
+
+    pub fn settlement_hook(mut self, hook: impl Into<String>) -> Self {
+        self.0.settlement_hook = Some(hook.into());
+        self
+    }
+
+    pub fn royalty(mut self, address: impl Into<String>, share: Decimal) -> Self {
+        self.0.royalties.push(RoyaltyMsg { address: address.into(), share });
+        self
+    }
+
+    pub fn vault(mut self, contract: impl Into<String>, yield_recipient: YieldRecipient) -> Self {
+        self.0.vault = Some(VaultMsg { contract: contract.into(), yield_recipient });
+        self
+    }
+
+    pub fn build(self) -> InstantiateMsg {
+        self.0
+    }
+}
+
+impl From<InstantiateMsgBuilder> for InstantiateMsg {
+    fn from(builder: InstantiateMsgBuilder) -> Self {
+        builder.0
+    }
+}
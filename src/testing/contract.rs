@@ -0,0 +1,392 @@
+use cosmwasm_std::{Addr, Coin, coin, coins, StdResult, Uint128};
+use cw_multi_test::{App, AppResponse, BasicApp, ContractWrapper, Executor};
+use cw_utils::Expiration;
+
+use crate::contract::{execute, instantiate, query, reply};
+use crate::error::ContractError;
+use crate::msg::{
+    AccessListResponse, AccountingResponse, ApprovalResponse, BidsResponse, ClaimableResponse, ConfigResponse,
+    EscrowResponse, ExecuteMsg, FailedSettlementsResponse, FeesResponse, InstantiateMsg, QueryMsg, WinnersResponse,
+};
+
+#[derive(Debug)]
+pub struct BiddingContract(Addr);
+
+impl BiddingContract {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn app_with_funds(sender: impl Into<Option<Addr>>, amount: impl Into<Option<u128>>) -> BasicApp {
+        App::new(|router, _api, storage| {
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &sender.into().unwrap_or_else(|| Addr::unchecked("owner")),
+                    coins(amount.into().unwrap_or(0), "atom"))
+                .unwrap();
+        })
+    }
+
+    pub fn store_code(app: &mut App) -> u64 {
+        let contract = ContractWrapper::new(execute, instantiate, query).with_reply(reply);
+        app.store_code(Box::new(contract))
+    }
+
+    #[track_caller]
+    pub fn instantiate<'a>(
+        app: &mut App,
+        code_id: u64,
+        sender: impl Into<Option<&'a Addr>>,
+        owner: impl Into<Option<&'a Addr>>,
+        admin: impl Into<Option<&'a Addr>>,
+        commodity: impl Into<Option<&'a str>>,
+        bid: impl Into<Option<u128>>,
+    ) -> StdResult<Self> {
+        let sender = sender.into().cloned().unwrap_or_else(|| Addr::unchecked("sender"));
+        let admin = admin.into().map(Addr::to_string);
+        let owner = owner.into().map(Addr::to_string);
+        let commodity = commodity.into().unwrap_or("gold").to_string();
+        let bid = bid.into().map(|b| vec![coin(b, "atom")]).unwrap_or_default();
+
+        app.instantiate_contract(
+            code_id,
+            sender,
+            &InstantiateMsg {
+                commodity,
+                owner,
+                ..InstantiateMsg::default()
+            },
+            bid.as_slice(),
+            "Bidding contract",
+            admin,
+        )
+            .map(BiddingContract)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn instantiate_with_msg(
+        app: &mut App,
+        code_id: u64,
+        sender: &Addr,
+        msg: &InstantiateMsg,
+        funds: &[Coin],
+    ) -> Result<Self, ContractError> {
+        app.instantiate_contract(code_id, sender.clone(), msg, funds, "Bidding contract", None)
+            .map(BiddingContract)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn make_bid(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        funds: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::MakeBid { units: None, price: None, lots: None, expires: None, on_behalf_of: None };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, funds)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn make_expiring_bid(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        expires: Expiration,
+        funds: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::MakeBid { units: None, price: None, lots: None, expires: Some(expires), on_behalf_of: None };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, funds)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn make_bid_on_behalf_of(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        beneficiary: &Addr,
+        funds: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::MakeBid {
+            units: None,
+            price: None,
+            lots: None,
+            expires: None,
+            on_behalf_of: Some(beneficiary.to_string()),
+        };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, funds)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn make_unit_bid(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        units: u64,
+        price: u128,
+        funds: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::MakeBid { units: Some(units), price: Some(Uint128::new(price)), lots: None, expires: None, on_behalf_of: None };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, funds)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn make_bundle_bid(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        lots: &[u32],
+        funds: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::MakeBid { units: None, price: None, lots: Some(lots.to_vec()), expires: None, on_behalf_of: None };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, funds)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn set_max_bid(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        max: u128,
+        funds: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::SetMaxBid { max: Uint128::new(max) }, funds)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn prune_expired(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        limit: Option<u32>,
+    ) -> Result<AppResponse, ContractError> {
+        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::PruneExpired { limit }, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn close(
+        &self,
+        app: &mut App,
+        sender: &Addr
+    ) -> Result<AppResponse, ContractError> {
+        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::Close {}, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn retract<'a>(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        receiver: impl Into<Option<&'a Addr>>,
+    ) -> Result<AppResponse, ContractError> {
+        let receiver = receiver.into().map(Addr::to_string);
+        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::Retract { receiver, on_behalf_of: None }, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn retract_on_behalf_of<'a>(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        beneficiary: &Addr,
+        receiver: impl Into<Option<&'a Addr>>,
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::Retract {
+            receiver: receiver.into().map(Addr::to_string),
+            on_behalf_of: Some(beneficiary.to_string()),
+        };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn approve_operator(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        operator: &Addr,
+        expires: impl Into<Option<Expiration>>,
+        max_amount: u128,
+        funds: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::ApproveOperator {
+            operator: operator.to_string(),
+            expires: expires.into(),
+            max_amount: Uint128::new(max_amount),
+        };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, funds)
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn revoke_operator(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        operator: &Addr,
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::RevokeOperator { operator: operator.to_string() };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn raise_bid(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        granter: &Addr,
+        amount: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::RaiseBid { granter: granter.to_string(), amount: amount.to_vec() };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn decrease_bid(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        amount: &[Coin],
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::DecreaseBid { amount: amount.to_vec() };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn claim_fees(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+    ) -> Result<AppResponse, ContractError> {
+        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::ClaimFees {}, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn withdraw<'a>(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        receiver: impl Into<Option<&'a Addr>>,
+    ) -> Result<AppResponse, ContractError> {
+        let receiver = receiver.into().map(Addr::to_string);
+        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::Withdraw { receiver }, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn update_access_list(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        add: &[&Addr],
+        remove: &[&Addr],
+    ) -> Result<AppResponse, ContractError> {
+        let add = add.iter().map(|addr| addr.to_string()).collect();
+        let remove = remove.iter().map(|addr| addr.to_string()).collect();
+        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::UpdateAccessList { add, remove }, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn register_outbid_hook(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        contract: &Addr,
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::RegisterOutbidHook { contract: contract.to_string() };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, &[])
+            .map_err(|err| err.downcast().unwrap())
+    }
+
+    #[track_caller]
+    pub fn query_config(&self, app: &App) -> StdResult<ConfigResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Config {})
+    }
+
+    #[track_caller]
+    pub fn query_bids(&self, app: &App) -> StdResult<BidsResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Bids {})
+    }
+
+    #[track_caller]
+    pub fn query_fees(&self, app: &App) -> StdResult<FeesResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Fees {})
+    }
+
+    #[track_caller]
+    pub fn query_access_list<'a>(
+        &self,
+        app: &App,
+        start_after: impl Into<Option<&'a Addr>>,
+        limit: impl Into<Option<u32>>,
+    ) -> StdResult<AccessListResponse> {
+        let start_after = start_after.into().map(Addr::to_string);
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::AccessList { start_after, limit: limit.into() })
+    }
+
+    #[track_caller]
+    pub fn query_winners(&self, app: &App) -> StdResult<WinnersResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Winners {})
+    }
+
+    #[track_caller]
+    pub fn query_approval(&self, app: &App, granter: &Addr, operator: &Addr) -> StdResult<ApprovalResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Approval {
+                granter: granter.to_string(),
+                operator: operator.to_string(),
+            })
+    }
+
+    #[track_caller]
+    pub fn query_accounting(&self, app: &App) -> StdResult<AccountingResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Accounting {})
+    }
+
+    #[track_caller]
+    pub fn query_failed_settlements(&self, app: &App) -> StdResult<FailedSettlementsResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::FailedSettlements {})
+    }
+
+    #[track_caller]
+    pub fn query_claimable(&self, app: &App, address: &Addr) -> StdResult<ClaimableResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Claimable { address: address.to_string() })
+    }
+
+    #[track_caller]
+    pub fn query_escrow(&self, app: &App, address: &Addr) -> StdResult<EscrowResponse> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Escrow { address: address.to_string() })
+    }
+}
+
+impl From<BiddingContract> for Addr {
+    fn from(contract: BiddingContract) -> Self {
+        contract.0
+    }
+}
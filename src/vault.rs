@@ -0,0 +1,117 @@
+use std::collections::BTreeSet;
+
+use cosmwasm_std::{
+    Addr, Coin, coin, Env, Order, QuerierWrapper, Response, StdResult, Storage, SubMsg, to_binary, Uint128, WasmMsg,
+};
+
+use crate::contract::{add_coin, non_zero, sub_coin};
+use crate::msg::{VaultBalanceResponse, VaultExecuteMsg, VaultQueryMsg};
+use crate::state::{CLOSED, COMMISSIONS, CONFIG, ESCROW, VAULT_PRINCIPAL};
+
+fn amount_of(coins: &[Coin], denom: &str) -> Uint128 {
+    coins.iter().find(|coin| coin.denom == denom).map(|coin| coin.amount).unwrap_or_default()
+}
+
+/// Escrow of every bidder less the commission already charged on it, which stays with the
+/// contract for the fee recipient.
+fn net_escrows(storage: &dyn Storage) -> StdResult<Vec<(Addr, Vec<Coin>)>> {
+    ESCROW.range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (address, mut escrow) = item?;
+            let charged = COMMISSIONS.may_load(storage, address.clone())?.unwrap_or_default();
+            charged.iter().try_for_each(|fee| sub_coin(&mut escrow, fee))?;
+            Ok((address, non_zero(&escrow)))
+        })
+        .collect()
+}
+
+/// Moves the difference between the net escrow and what is in the vault: escrow that came in is
+/// deposited after `resp`, escrow that goes out is withdrawn ahead of the transfers in `resp`.
+/// Nothing is kept in the vault once the auction is closed.
+pub fn rebalance(storage: &mut dyn Storage, mut resp: Response) -> StdResult<Response> {
+    let vault = match CONFIG.load(storage)?.vault {
+        Some(vault) => vault.contract,
+        None => return Ok(resp),
+    };
+
+    let mut target = vec![];
+    if !CLOSED.load(storage)? {
+        for (_, escrow) in net_escrows(storage)? {
+            escrow.iter().for_each(|amount| add_coin(&mut target, amount));
+        }
+    }
+    let principal = VAULT_PRINCIPAL.may_load(storage)?.unwrap_or_default();
+
+    let mut deposit = vec![];
+    let mut withdrawal = vec![];
+    let denoms: BTreeSet<_> = target.iter().chain(&principal).map(|coin| coin.denom.as_str()).collect();
+    for denom in denoms {
+        let (wanted, held) = (amount_of(&target, denom), amount_of(&principal, denom));
+        if wanted > held {
+            deposit.push(coin((wanted - held).u128(), denom));
+        } else if held > wanted {
+            withdrawal.push(coin((held - wanted).u128(), denom));
+        }
+    }
+    VAULT_PRINCIPAL.save(storage, &target)?;
+
+    if !withdrawal.is_empty() {
+        let msg = to_binary(&VaultExecuteMsg::Withdraw { amount: withdrawal })?;
+        resp.messages.insert(0, SubMsg::new(WasmMsg::Execute { contract_addr: vault.to_string(), msg, funds: vec![] }));
+    }
+    if !deposit.is_empty() {
+        let msg = to_binary(&VaultExecuteMsg::Deposit {})?;
+        resp = resp.add_message(WasmMsg::Execute { contract_addr: vault.to_string(), msg, funds: deposit });
+    }
+    Ok(resp)
+}
+
+/// Withdraws everything from the vault on close. Returns the withdrawal, if there is anything to
+/// withdraw, and the yield accrued on top of the principal.
+pub fn withdraw_all(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    vault: &Addr,
+) -> StdResult<(Option<WasmMsg>, Vec<Coin>)> {
+    let balance: VaultBalanceResponse = querier.query_wasm_smart(
+        vault,
+        &VaultQueryMsg::Balance { address: env.contract.address.to_string() },
+    )?;
+    let principal = VAULT_PRINCIPAL.may_load(storage)?.unwrap_or_default();
+    VAULT_PRINCIPAL.save(storage, &vec![])?;
+
+    let accrued: Vec<_> = balance.amount.iter()
+        .map(|held| coin(held.amount.saturating_sub(amount_of(&principal, &held.denom)).u128(), &held.denom))
+        .collect();
+
+    let amount = non_zero(&balance.amount);
+    if amount.is_empty() {
+        return Ok((None, non_zero(&accrued)));
+    }
+    let msg = to_binary(&VaultExecuteMsg::Withdraw { amount })?;
+    Ok((Some(WasmMsg::Execute { contract_addr: vault.to_string(), msg, funds: vec![] }), non_zero(&accrued)))
+}
+
+/// Splits `accrued` among the bidders pro rata to their net escrow, rounding down.
+pub fn yield_shares(storage: &dyn Storage, accrued: &[Coin]) -> StdResult<Vec<(Addr, Vec<Coin>)>> {
+    let escrows = net_escrows(storage)?;
+    let mut total = vec![];
+    for (_, escrow) in &escrows {
+        escrow.iter().for_each(|amount| add_coin(&mut total, amount));
+    }
+
+    let shares = escrows.into_iter()
+        .map(|(address, escrow)| {
+            let share: Vec<_> = escrow.iter()
+                .map(|amount| {
+                    let accrued = amount_of(accrued, &amount.denom);
+                    coin(accrued.multiply_ratio(amount.amount, amount_of(&total, &amount.denom)).u128(), &amount.denom)
+                })
+                .collect();
+            (address, non_zero(&share))
+        })
+        .filter(|(_, share)| !share.is_empty())
+        .collect();
+    Ok(shares)
+}